@@ -1,19 +1,22 @@
 extern crate c4_e5_chess;
 
-use std::str::FromStr;
+use std::{hint::black_box, str::FromStr};
 
 use c4_e5_chess::{
     engine::{game::Game, move_gen::MoveGenPrime},
-    eval::{evaluation::Evaluation, simple::Simple},
+    eval::{evaluation::Evaluation, simple::Simple, tapered::Tapered},
 };
 use cozy_chess::{Board, Move};
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, Criterion};
 
 pub fn criterion_evaluate(c: &mut Criterion) {
     let board = Board::default();
     c.bench_function("evaluate_simple", |b| {
         b.iter(|| Simple::evaluate(black_box(&board)))
     });
+    c.bench_function("evaluate_tapered", |b| {
+        b.iter(|| Tapered::evaluate(black_box(&board)))
+    });
 }
 
 pub fn criterion_movegen(c: &mut Criterion) {
@@ -29,6 +32,10 @@ pub fn criterion_find_move(c: &mut Criterion) {
     let fen = "4r1k1/5bpp/2p5/3pr3/8/1B3pPq/PPR2P2/2R2QK1 b - - 0 1";
     let mut game = Game::new(fen.to_string(), 0, 15000);
     c.bench_function("find_move", |b| b.iter(|| black_box(&mut game).find_move()));
+    println!(
+        "find_move: pawn hash hit rate {:.1}%",
+        game.pawn_hash_stats().hit_rate()
+    );
 }
 
 criterion_group!(
@@ -1,26 +1,46 @@
-use super::time_management::TimeManagement;
-use crate::engine::game::Game;
+use super::{
+    options::{find_option, options, UciOption},
+    time_management::TimeManagement,
+};
+use crate::engine::{
+    constants::{DEFAULT_TIME, INIT_MAX_DEPTH},
+    game::Game,
+    report::{Score, SearchInfo},
+    store::Bound,
+};
 use crate::misc::types::*;
 use cozy_chess::{util, Board, Color};
 use log::{error, info};
 use std::{
     io::stdin,
     str::{FromStr, SplitWhitespace},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
 };
 
 /// An UCI interface to be used with a chess GUI.
 /// See https://en.wikipedia.org/wiki/Universal_Chess_Interface .
 pub struct Cli {
-    game: Game,
+    /// The game, none while it is searched in the background
+    game: Option<Game>,
     tm: TimeManagement,
+    playing: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+    search: Option<JoinHandle<Game>>,
 }
 
 impl Cli {
     /// Constructor
     pub fn new() -> Cli {
         Cli {
-            game: Default::default(),
+            game: Some(Game::default()),
             tm: TimeManagement::default(),
+            playing: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(false)),
+            search: None,
         }
     }
 
@@ -49,6 +69,10 @@ impl Cli {
                             self.send_ready_ok();
                         }
 
+                        "ucinewgame" => {
+                            self.game().new_game();
+                        }
+
                         "position" => {
                             self.position(args);
                         }
@@ -57,7 +81,22 @@ impl Cli {
                             self.go(args);
                         }
 
-                        "quit" => return,
+                        "setoption" => {
+                            self.set_option(args);
+                        }
+
+                        "stop" => {
+                            self.stop();
+                        }
+
+                        "ponderhit" => {
+                            self.pondering.store(false, Ordering::Relaxed);
+                        }
+
+                        "quit" => {
+                            self.stop();
+                            return;
+                        }
 
                         _ => continue,
                     }
@@ -69,6 +108,7 @@ impl Cli {
 
     /// UCI `position` command
     fn position(&mut self, mut args: SplitWhitespace) {
+        let game = self.game();
         while let Some(cmd) = args.next() {
             match cmd {
                 "fen" => {
@@ -80,7 +120,7 @@ impl Cli {
                                 if i == 5 {
                                     // move count
                                     match s.parse::<MoveNumber>() {
-                                        Ok(n) => self.game.move_number = n,
+                                        Ok(n) => game.move_number = n,
                                         Err(_) => error!("No move number in FEN"),
                                     }
                                 }
@@ -93,7 +133,7 @@ impl Cli {
                     }
                     fen = fen.trim_end().to_string();
                     match Board::from_str(fen.as_str()) {
-                        Ok(b) => self.game.board = b,
+                        Ok(b) => game.set_board(b),
                         Err(e) => {
                             error!("FEN not valid: {e}");
                             return;
@@ -102,27 +142,26 @@ impl Cli {
                 }
 
                 "startpos" => {
-                    self.game = Game::default();
+                    game.set_board(Board::default());
+                    game.move_number = 0;
                 }
 
                 "moves" => loop {
                     match args.next() {
-                        Some(move_string) => {
-                            match util::parse_uci_move(&self.game.board, move_string) {
-                                Ok(m) => {
-                                    info!("Move: {move_string}");
-                                    self.game.game_history.inc(&self.game.board);
-                                    self.game.board.play_unchecked(m);
-                                    if self.game.board.side_to_move() == Color::Black {
-                                        self.game.move_number += 1;
-                                    }
-                                }
-                                Err(_) => {
-                                    error!("Illegal move");
-                                    return;
+                        Some(move_string) => match util::parse_uci_move(&game.board, move_string) {
+                            Ok(m) => {
+                                info!("Move: {move_string}");
+                                game.game_history.inc(&game.board);
+                                game.board.play_unchecked(m);
+                                if game.board.side_to_move() == Color::Black {
+                                    game.move_number += 1;
                                 }
                             }
-                        }
+                            Err(_) => {
+                                error!("Illegal move");
+                                return;
+                            }
+                        },
                         None => return,
                     }
                 },
@@ -133,12 +172,39 @@ impl Cli {
     }
 
     /// UCI `go` command
-    fn go(&mut self, mut args: SplitWhitespace) {
+    fn go(&mut self, args: SplitWhitespace) {
+        let mut game = self.take_game();
+        let mut fixed_time = false;
+        let mut ponder = false;
+        self.tm = TimeManagement {
+            move_overhead: self.tm.move_overhead,
+            ..Default::default()
+        };
+        game.max_depth = INIT_MAX_DEPTH;
+        game.move_time = DEFAULT_TIME;
+        game.infinite = false;
+        game.max_nodes = None;
+        game.mate_in = None;
+        game.search_moves.clear();
+
+        let mut args = args.peekable();
         while let Some(cmd) = args.next() {
             match cmd {
-                "searchmoves" => {}
+                "searchmoves" => {
+                    while let Some(m) = args
+                        .peek()
+                        .and_then(|s| util::parse_uci_move(&game.board, s).ok())
+                    {
+                        if game.board.is_legal(m) {
+                            game.search_moves.push(m);
+                        } else {
+                            error!("Illegal search move: {m}");
+                        }
+                        args.next();
+                    }
+                }
 
-                "ponder" => {}
+                "ponder" => ponder = true,
 
                 "wtime" => match args.next() {
                     Some(arg) => match arg.parse() {
@@ -182,20 +248,47 @@ impl Cli {
 
                 "depth" => match args.next() {
                     Some(arg) => match arg.parse() {
-                        Ok(a) => self.game.max_depth = a,
+                        Ok(a) => game.max_depth = engine_depth(a),
                         Err(_) => break,
                     },
                     None => break,
                 },
 
-                "nodes" => {}
+                "nodes" => match args.next() {
+                    Some(arg) => match arg.parse() {
+                        Ok(a) => {
+                            game.max_nodes = Some(a);
+                            if !fixed_time {
+                                // the node count is the only limit unless a time is given
+                                game.move_time = MoveTime::MAX;
+                            }
+                        }
+                        Err(_) => break,
+                    },
+                    None => break,
+                },
 
-                "mate" => {}
+                "mate" => match args.next() {
+                    Some(arg) => match arg.parse() {
+                        Ok(a) => {
+                            game.mate_in = Some(a);
+                            if !fixed_time {
+                                // search until the mate is proven or refuted unless a time is given
+                                game.move_time = MoveTime::MAX;
+                            }
+                        }
+                        Err(_) => break,
+                    },
+                    None => break,
+                },
+
+                "infinite" => game.infinite = true,
 
                 "movetime" => match args.next() {
                     Some(arg) => match arg.parse::<u64>() {
                         Ok(a) => {
-                            self.game.move_time = a * 9 / 10;
+                            game.move_time = a * 9 / 10;
+                            fixed_time = true;
                         }
                         Err(_) => break,
                     },
@@ -205,51 +298,203 @@ impl Cli {
                 _ => break,
             }
         }
-        self.tm.set_game_time(&mut self.game);
-        self.get_move_from_engine();
+        if !fixed_time {
+            self.tm.set_game_time(&mut game);
+        }
+        game.set_reporter(Box::new(send_info));
+        game.pondering().store(ponder, Ordering::Relaxed);
+        self.start_search(game);
+    }
+
+    /// UCI `setoption` command: `setoption name <id> [value <x>]`
+    fn set_option(&mut self, mut args: SplitWhitespace) {
+        if args.next() != Some("name") {
+            error!("No option name given");
+            return;
+        }
+        let mut name: Vec<&str> = vec![];
+        for arg in args.by_ref() {
+            if arg == "value" {
+                break;
+            }
+            name.push(arg);
+        }
+        let name = name.join(" ");
+        let value = args.collect::<Vec<&str>>().join(" ");
+
+        match find_option(&name) {
+            Some(option) => self.apply_option(&option, &value),
+            None => error!("Unknown option: {name}"),
+        }
+    }
+
+    /// Apply the value of an option to the engine.
+    fn apply_option(&mut self, option: &UciOption, value: &str) {
+        let spin_value = option.spin_value(value);
+        let check_value = option.check_value(value);
+        let game = self.game();
+        match (option.name, spin_value, check_value) {
+            ("Hash", Some(v), _) => game.set_hash_size(v as usize),
+            ("Clear Hash", _, _) => game.clear_hash(),
+            ("Threads", Some(v), _) => game.set_threads(v as usize),
+            ("Move Overhead", Some(v), _) => self.tm.move_overhead = v as MoveTime,
+            // pondering is requested by the GUI using `go ponder`
+            ("Ponder", _, Some(_)) => {}
+            ("MultiPV", Some(v), _) => game.multi_pv = v as usize,
+            ("NullMove", _, Some(v)) => game.null_move_pruning = v,
+            ("ForwardPruningDepthStart", Some(v), _) => {
+                game.forward_pruning_depth_start = v as Depth
+            }
+            ("ForwardPruningMinimum", Some(v), _) => game.forward_pruning_minimum = v as usize,
+            ("ForwardPruningRatio", Some(v), _) => game.forward_pruning_ratio = v as usize,
+            _ => {
+                error!("Invalid value for option {}: {value}", option.name);
+                return;
+            }
+        }
+        info!("Option {} was set to {value}", option.name);
+    }
+
+    /// UCI `stop` command: end the search, the best move found so far is sent.
+    fn stop(&mut self) {
+        self.playing.store(false, Ordering::Relaxed);
+        self.wait_for_search();
+    }
+
+    /// Start the search in the background, so that commands can be processed meanwhile.
+    /// The game is moved to the search thread and given back when the search has finished.
+    fn start_search(&mut self, mut game: Game) {
+        self.playing = game.playing();
+        self.pondering = game.pondering();
+        self.playing.store(true, Ordering::Relaxed);
+        self.search = Some(thread::spawn(move || {
+            Self::get_move_from_engine(&mut game);
+            game
+        }));
+    }
+
+    /// Wait until the background search has finished and take back the game.
+    fn wait_for_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            match search.join() {
+                Ok(game) => self.game = Some(game),
+                Err(_) => error!("Search aborted"),
+            }
+        }
+    }
+
+    /// Get the game, once the background search has finished.
+    fn game(&mut self) -> &mut Game {
+        self.wait_for_search();
+        self.game.get_or_insert_with(Game::default)
+    }
+
+    /// Take the game to be searched, once the background search has finished.
+    fn take_game(&mut self) -> Game {
+        self.wait_for_search();
+        self.game.take().unwrap_or_default()
     }
 
     /// Get best move from the engine module.
-    fn get_move_from_engine(&mut self) {
-        match self.game.find_move() {
+    fn get_move_from_engine(game: &mut Game) {
+        match game.search() {
             Some(m) => {
-                let result_uci = util::display_uci_move(&self.game.board, m);
-                self.game.game_history.inc(&self.game.board);
-                self.game.board.play_unchecked(m);
-                let result = format!("bestmove {result_uci}");
-                info!("{} nodes examined.", self.game.node_count);
-                self.send_string(result.as_str());
+                let result_uci = util::display_uci_move(&game.board, m);
+                game.game_history.inc(&game.board);
+                game.board.play_unchecked(m);
+                let result = match game.ponder_move() {
+                    Some(p) => format!(
+                        "bestmove {result_uci} ponder {}",
+                        util::display_uci_move(&game.board, p)
+                    ),
+                    None => format!("bestmove {result_uci}"),
+                };
+                info!("{} nodes examined.", game.node_count);
+                send_string(result.as_str());
+            }
+            None => {
+                // every `go` is answered, a null move if there is no legal move
+                error!("No valid move found");
+                send_string("bestmove 0000");
             }
-            None => error!("No valid move found"),
         }
     }
 
     /// Send name and author.
     fn send_id(&self) {
-        self.send_string("id name C4-E5 Chess");
-        self.send_string("id author Eugen Lindorfer");
+        send_string("id name C4-E5 Chess");
+        send_string("id author Eugen Lindorfer");
     }
 
     /// Send `options`.
     fn send_options(&self) {
-        self.send_string("option"); //TODO extend this
+        for option in options() {
+            send_string(option.to_string().as_str());
+        }
     }
 
     /// Send `uci ok`.
     fn send_uci_ok(&self) {
-        self.send_string("uciok");
+        send_string("uciok");
     }
 
     /// Send `readyok`.
     fn send_ready_ok(&self) {
-        self.send_string("readyok");
+        send_string("readyok");
     }
+}
+
+/// Convert the depth of `go depth`, which counts the root move like the reported depths,
+/// into the engine's depth, which does not.
+fn engine_depth(depth: Depth) -> Depth {
+    (depth - 1).max(0)
+}
+
+/// Output and log a string.
+fn send_string(s: &str) {
+    println!("{s}");
+    info!("|   {s}");
+}
 
-    /// Output and log a string.
-    fn send_string(&self, s: &str) {
-        println!("{s}");
-        info!("|   {s}");
+/// Send the search progress as UCI `info`.
+fn send_info(board: &Board, search_info: &SearchInfo) {
+    send_string(format_info(board, search_info).as_str());
+}
+
+/// Format the search progress as UCI `info`, the principal variation is given for the root position.
+fn format_info(board: &Board, search_info: &SearchInfo) -> String {
+    let score = match search_info.score {
+        Score::Cp(cp) => format!("cp {cp}"),
+        Score::Mate(n) => format!("mate {n}"),
+    };
+    let score = match search_info.bound {
+        Bound::Exact => score,
+        Bound::Lower => score + " lowerbound",
+        Bound::Upper => score + " upperbound",
+    };
+    let mut result = format!(
+        "info depth {} seldepth {} multipv {} score {} nodes {} nps {} time {} hashfull {}",
+        search_info.depth,
+        search_info.sel_depth,
+        search_info.multi_pv,
+        score,
+        search_info.nodes,
+        search_info.nps,
+        search_info.time,
+        search_info.hashfull
+    );
+    if let Some(mv) = search_info.curr_move {
+        result += &format!(" currmove {}", util::display_uci_move(board, mv));
     }
+    if !search_info.pv.is_empty() {
+        result += " pv";
+        let mut b = board.clone();
+        for mv in &search_info.pv {
+            result += &format!(" {}", util::display_uci_move(&b, *mv));
+            b.play_unchecked(*mv);
+        }
+    }
+    result
 }
 
 impl Default for Cli {
@@ -257,3 +502,71 @@ impl Default for Cli {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cozy_chess::Move;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_engine_depth() {
+        assert_eq!(engine_depth(0), 0);
+        assert_eq!(engine_depth(1), 0);
+
+        // the deepest iteration reported is the depth given by `go depth`
+        let depths = Arc::new(Mutex::new(Vec::new()));
+        let depths_clone = depths.clone();
+        let mut game = Game::new("".to_string(), engine_depth(4), 60_000);
+        game.set_threads(1);
+        game.set_reporter(Box::new(move |_, info: &SearchInfo| {
+            depths_clone.lock().unwrap().push(info.depth)
+        }));
+        game.find_move().unwrap();
+        assert_eq!(depths.lock().unwrap().iter().max(), Some(&4));
+    }
+
+    #[test]
+    fn test_format_info() {
+        let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut search_info = SearchInfo {
+            depth: 3,
+            sel_depth: 4,
+            multi_pv: 1,
+            score: Score::Cp(25),
+            bound: Bound::Exact,
+            nodes: 1000,
+            nps: 50000,
+            time: 20,
+            hashfull: 1,
+            curr_move: Some(Move::from_str("e1h1").unwrap()),
+            pv: vec![
+                Move::from_str("e1h1").unwrap(),
+                Move::from_str("e8a8").unwrap(),
+            ],
+        };
+        assert_eq!(
+            format_info(&board, &search_info),
+            "info depth 3 seldepth 4 multipv 1 score cp 25 nodes 1000 nps 50000 time 20 hashfull 1 currmove e1g1 pv e1g1 e8c8"
+        );
+
+        search_info.multi_pv = 2;
+        search_info.score = Score::Mate(-2);
+        search_info.curr_move = None;
+        search_info.pv.clear();
+        assert_eq!(
+            format_info(&board, &search_info),
+            "info depth 3 seldepth 4 multipv 2 score mate -2 nodes 1000 nps 50000 time 20 hashfull 1"
+        );
+
+        search_info.multi_pv = 1;
+        search_info.score = Score::Cp(-40);
+        search_info.bound = Bound::Upper;
+        assert_eq!(
+            format_info(&board, &search_info),
+            "info depth 3 seldepth 4 multipv 1 score cp -40 upperbound nodes 1000 nps 50000 time 20 hashfull 1"
+        );
+        search_info.bound = Bound::Lower;
+        assert!(format_info(&board, &search_info).contains("score cp -40 lowerbound nodes"));
+    }
+}
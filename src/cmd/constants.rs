@@ -12,3 +12,16 @@ pub const MOVE_TIME_FRACTION_LATE_GAME: u64 = 30;
 
 /// Start move for late game
 pub const MOVE_LATE_GAME_START: MoveNumber = 25;
+
+/// Default time in milliseconds to be reserved for communication with the GUI
+pub const DEFAULT_MOVE_OVERHEAD: MoveTime = 10;
+
+// UCI options
+/// Maximal size of the transposition table in megabytes
+pub const MAX_HASH_SIZE: usize = 65_536;
+
+/// Maximal number of search threads
+pub const MAX_THREADS: usize = 512;
+
+/// Maximal number of principal variations to be reported
+pub const MAX_MULTI_PV: usize = 256;
@@ -4,5 +4,8 @@ pub mod cli;
 /// Some useful constants.
 pub mod constants;
 
+/// Options which can be set by the GUI.
+pub mod options;
+
 /// Time management: decide how much time will be spent for each move.
 pub mod time_management;
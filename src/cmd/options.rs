@@ -0,0 +1,169 @@
+use super::constants::*;
+use crate::engine::constants::*;
+use std::fmt;
+
+/// The type of an UCI option including its default value and limits.
+pub enum OptionType {
+    Check { default: bool },
+    Spin { default: i64, min: i64, max: i64 },
+    Button,
+}
+
+/// An option of the engine which can be changed by the GUI.
+pub struct UciOption {
+    pub name: &'static str,
+    pub option_type: OptionType,
+}
+
+impl UciOption {
+    /// Parse the value of a `spin` option and keep it within its limits.
+    pub fn spin_value(&self, value: &str) -> Option<i64> {
+        match self.option_type {
+            OptionType::Spin { min, max, .. } => {
+                value.parse::<i64>().ok().map(|v| v.clamp(min, max))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse the value of a `check` option.
+    pub fn check_value(&self, value: &str) -> Option<bool> {
+        match self.option_type {
+            OptionType::Check { .. } => value.parse::<bool>().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UciOption {
+    /// Format the option as UCI `option` command.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+        match self.option_type {
+            OptionType::Check { default } => write!(f, "check default {default}"),
+            OptionType::Spin { default, min, max } => {
+                write!(f, "spin default {default} min {min} max {max}")
+            }
+            OptionType::Button => write!(f, "button"),
+        }
+    }
+}
+
+/// All options offered to the GUI.
+pub fn options() -> Vec<UciOption> {
+    vec![
+        UciOption {
+            name: "Hash",
+            option_type: OptionType::Spin {
+                default: DEFAULT_HASH_SIZE as i64,
+                min: 1,
+                max: MAX_HASH_SIZE as i64,
+            },
+        },
+        UciOption {
+            name: "Clear Hash",
+            option_type: OptionType::Button,
+        },
+        UciOption {
+            name: "Threads",
+            option_type: OptionType::Spin {
+                default: DEFAULT_THREADS as i64,
+                min: 1,
+                max: MAX_THREADS as i64,
+            },
+        },
+        UciOption {
+            name: "Move Overhead",
+            option_type: OptionType::Spin {
+                default: DEFAULT_MOVE_OVERHEAD as i64,
+                min: 0,
+                max: 5000,
+            },
+        },
+        UciOption {
+            name: "Ponder",
+            option_type: OptionType::Check { default: false },
+        },
+        UciOption {
+            name: "MultiPV",
+            option_type: OptionType::Spin {
+                default: 1,
+                min: 1,
+                max: MAX_MULTI_PV as i64,
+            },
+        },
+        UciOption {
+            name: "NullMove",
+            option_type: OptionType::Check { default: true },
+        },
+        UciOption {
+            name: "ForwardPruningDepthStart",
+            option_type: OptionType::Spin {
+                default: FORWARD_PRUNING_DEPTH_START as i64,
+                min: 0,
+                max: INIT_MAX_DEPTH as i64,
+            },
+        },
+        UciOption {
+            name: "ForwardPruningMinimum",
+            option_type: OptionType::Spin {
+                default: FORWARD_PRUNING_MINIMUM as i64,
+                min: 1,
+                max: 256,
+            },
+        },
+        UciOption {
+            name: "ForwardPruningRatio",
+            option_type: OptionType::Spin {
+                default: FORWARD_PRUNING_RATIO as i64,
+                min: 1,
+                max: 256,
+            },
+        },
+    ]
+}
+
+/// Find an option by its name, which is not case sensitive.
+pub fn find_option(name: &str) -> Option<UciOption> {
+    options()
+        .into_iter()
+        .find(|o| o.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let hash = find_option("hash").unwrap();
+        assert_eq!(
+            hash.to_string(),
+            format!(
+                "option name Hash type spin default {DEFAULT_HASH_SIZE} min 1 max {MAX_HASH_SIZE}"
+            )
+        );
+        assert_eq!(hash.spin_value("64"), Some(64));
+        assert_eq!(hash.spin_value("0"), Some(1));
+        assert_eq!(hash.spin_value("x"), None);
+        assert_eq!(hash.check_value("true"), None);
+
+        let ponder = find_option("Ponder").unwrap();
+        assert_eq!(
+            ponder.to_string(),
+            "option name Ponder type check default false"
+        );
+        assert_eq!(ponder.check_value("true"), Some(true));
+
+        let clear_hash = find_option("clear hash").unwrap();
+        assert_eq!(clear_hash.to_string(), "option name Clear Hash type button");
+
+        let threads = find_option("Threads").unwrap();
+        assert_eq!(
+            threads.to_string(),
+            format!("option name Threads type spin default 1 min 1 max {MAX_THREADS}")
+        );
+
+        assert!(find_option("Unknown").is_none());
+    }
+}
@@ -6,13 +6,13 @@ use log::info;
 use std::cmp::{max, min};
 
 /// A structure holing available time and increment and the number of moves until next time control.
-#[derive(Default)]
 pub struct TimeManagement {
     pub white_time: MoveTime,
     pub black_time: MoveTime,
     pub white_inc: MoveTime,
     pub black_inc: MoveTime,
     pub moves_to_go: MoveNumber,
+    pub move_overhead: MoveTime,
 }
 
 impl TimeManagement {
@@ -40,10 +40,29 @@ impl TimeManagement {
             inc_avail = self.black_inc;
         }
 
+        if time_avail == 0 {
+            info!("No time given, movetime remains {}", g.move_time);
+            return;
+        }
+        let time_avail = time_avail.saturating_sub(self.move_overhead).max(1);
+
         move_time = time_avail / move_time_fraction(g.move_number) + inc_avail / 2;
-        move_time = min(move_time, time_avail - MIN_MOVE_TIME);
+        move_time = min(move_time, time_avail.saturating_sub(MIN_MOVE_TIME));
         move_time = max(move_time, MIN_MOVE_TIME);
         g.move_time = move_time;
         info!("Movetime was set to {move_time}");
     }
 }
+
+impl Default for TimeManagement {
+    fn default() -> Self {
+        Self {
+            white_time: 0,
+            black_time: 0,
+            white_inc: 0,
+            black_inc: 0,
+            moves_to_go: 0,
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+        }
+    }
+}
@@ -23,12 +23,63 @@ pub const FORWARD_PRUNING_MINIMUM: usize = 4;
 /// Ratio w.r.t. score for moves to keep during forward pruning
 pub const FORWARD_PRUNING_RATIO: usize = 4;
 
+/// Number of quiescence plies in which all evasions of a check are searched
+pub const QUIESCENCE_CHECK_DEPTH: Depth = 2;
+
+/// Minimal remaining depth for null move pruning
+pub const NULL_MOVE_DEPTH_MIN: Depth = 3;
+
+/// Minimal remaining depth to verify a null move cutoff by a reduced search
+pub const NULL_MOVE_VERIFICATION_DEPTH: Depth = 6;
+
+/// Minimal remaining depth for late move reductions
+pub const LMR_DEPTH_MIN: Depth = 3;
+
+/// Number of moves searched to full depth before late move reductions apply
+pub const LMR_MOVES_MIN: usize = 3;
+
+/// Maximal number of plies a line is extended by
+pub const EXTENSIONS_MAX: Depth = 8;
+
+/// Minimal remaining depth to extend a singular move
+pub const SINGULAR_DEPTH_MIN: Depth = 6;
+
+/// Margin per ply of remaining depth by which the other moves must fail low for a singular move
+pub const SINGULAR_MARGIN: MoveScore = 4;
+
+/// Minimal depth to search the root moves within an aspiration window
+pub const ASPIRATION_DEPTH_MIN: Depth = 4;
+
+/// Initial half width of the aspiration window, doubled on every fail high or fail low
+pub const ASPIRATION_WINDOW: MoveScore = 40;
+
+// Move ordering
+/// Limit of the history scores of quiet moves
+pub const HISTORY_MAX: MoveScore = 16_384;
+
+// Lazy SMP
+/// Default number of search threads, more are used only if requested
+pub const DEFAULT_THREADS: usize = 1;
+
+// Transposition table
+/// Default size of the transposition table in megabytes
+pub const DEFAULT_HASH_SIZE: usize = 16;
+
 // Game
 /// Default time for one move
 pub const DEFAULT_TIME: MoveTime = 10_000; // in Milliseconds
 
+/// Interval to check whether an infinite search was stopped
+pub const WAIT_TIME: MoveTime = 5; // in Milliseconds
+
 // Evaluation
 /// Score above which a game is considered as won
 pub const MATE_LEVEL: MoveScore = 55_000;
 /// Score for mate
 pub const MATE: MoveScore = 60_000;
+
+/// Score of a pawn, used to convert scores to centipawns
+pub const PAWN_VALUE: MoveScore = 200;
+
+/// Material values of pawn, knight, bishop, rook, queen and king, used for exchanges
+pub const PIECE_VALUES: [MoveScore; 6] = [PAWN_VALUE, 600, 620, 950, 1800, 20_000];
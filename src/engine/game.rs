@@ -1,17 +1,27 @@
-use super::{constants::*, history::History, move_gen::MoveGenPrime, pvs::Pvs, store::Store};
+use super::{
+    constants::*,
+    history::History,
+    move_gen::MoveGenPrime,
+    pvs::Pvs,
+    report::{Line, Reporter, Score, SearchInfo},
+    store::{Bound, Store},
+};
+use crate::eval::pawn_hash::{PawnHash, PawnHashStats};
 use crate::misc::types::*;
 use core::time::Duration;
 use cozy_chess::{Board, Move};
 use log::{error, info};
-use rayon::prelude::*;
 use std::{
-    cmp::max,
+    cmp::{max, Reverse},
+    mem,
     str::FromStr,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{channel, RecvTimeoutError, Sender},
         Arc,
     },
-    thread::{self, JoinHandle},
+    thread,
+    time::Instant,
 };
 
 /// A chess game
@@ -20,10 +30,24 @@ pub struct Game {
     pub board: Board,
     pub move_time: MoveTime, // in Milliseconds
     pub move_number: MoveNumber,
+    pub infinite: bool,
+    pub max_nodes: Option<u64>,
+    pub mate_in: Option<Depth>,
+    pub search_moves: Vec<Move>,
+    pub multi_pv: usize,
+    pub null_move_pruning: bool,
+    pub forward_pruning_depth_start: Depth,
+    pub forward_pruning_minimum: usize,
+    pub forward_pruning_ratio: usize,
     playing: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
     pub node_count: u64,
-    game_store: Store,
+    game_store: Arc<Store>,
     pub game_history: History,
+    lines: Vec<Line>,
+    reporter: Option<Reporter>,
+    threads: usize,
+    pawn_hashes: Vec<PawnHash>,
 }
 
 impl Game {
@@ -38,15 +62,29 @@ impl Game {
                 },
                 board,
                 playing: Arc::new(AtomicBool::new(true)),
+                pondering: Arc::new(AtomicBool::new(false)),
                 move_time: if move_time == 0 {
                     DEFAULT_TIME
                 } else {
                     move_time
                 },
                 move_number: 0,
+                infinite: false,
+                max_nodes: None,
+                mate_in: None,
+                search_moves: Vec::new(),
+                multi_pv: 1,
+                null_move_pruning: true,
+                forward_pruning_depth_start: FORWARD_PRUNING_DEPTH_START,
+                forward_pruning_minimum: FORWARD_PRUNING_MINIMUM,
+                forward_pruning_ratio: FORWARD_PRUNING_RATIO,
                 node_count: 0,
-                game_store: Store::new(),
+                game_store: Arc::new(Store::new()),
                 game_history: History::new(),
+                lines: Vec::new(),
+                reporter: None,
+                threads: DEFAULT_THREADS,
+                pawn_hashes: Vec::new(),
             },
             Err(e) => {
                 error!("FEN not valid: {e}");
@@ -55,105 +93,302 @@ impl Game {
         }
     }
 
+    /// Set up a new position, keeping the transposition table and the settings.
+    /// The position history starts anew.
+    pub fn set_board(&mut self, board: Board) {
+        self.board = board;
+        self.game_history = History::new();
+    }
+
     /// Set a timer to stop playing after the move time has elapsed.
-    pub fn set_timer(&mut self) -> JoinHandle<()> {
-        self.playing.store(true, Ordering::Relaxed);
+    /// While pondering, the move time starts not before the pondering has ended.
+    /// The timer is cancelled as soon as the returned sender is dropped.
+    pub fn set_timer(&mut self) -> Sender<()> {
         let playing_clone = self.playing.clone();
+        let pondering_clone = self.pondering.clone();
         let move_time = self.move_time;
+        let (tx, rx) = channel();
         thread::spawn(move || {
-            thread::sleep(Duration::from_millis(move_time));
-            playing_clone.store(false, Ordering::Relaxed);
-        })
+            while pondering_clone.load(Ordering::Relaxed) {
+                if let Err(RecvTimeoutError::Disconnected) =
+                    rx.recv_timeout(Duration::from_millis(WAIT_TIME))
+                {
+                    return;
+                }
+            }
+            if let Err(RecvTimeoutError::Timeout) =
+                rx.recv_timeout(Duration::from_millis(move_time))
+            {
+                playing_clone.store(false, Ordering::Relaxed);
+            }
+        });
+        tx
+    }
+
+    /// Give the flag which keeps the search running, setting it to false stops the search.
+    pub fn playing(&self) -> Arc<AtomicBool> {
+        self.playing.clone()
+    }
+
+    /// Give the flag which marks a search on the opponent's time, setting it to false starts the move time.
+    pub fn pondering(&self) -> Arc<AtomicBool> {
+        self.pondering.clone()
+    }
+
+    /// Give the move expected as the opponent's reply to the best move found by the last search.
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.pv().get(1).copied()
+    }
+
+    /// Give the principal variation found by the last search.
+    pub fn pv(&self) -> &[Move] {
+        self.lines.first().map_or(&[], |l| &l.pv)
+    }
+
+    /// Give the score of the best move found by the last search.
+    pub fn score(&self) -> Option<Score> {
+        self.lines.first().map(|l| l.score)
+    }
+
+    /// Give the best root moves found by the last search, at most `multi_pv` of them, best first.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Search the best lines, at most `multi_pv` of them, best first.
+    pub fn find_lines(&mut self) -> Vec<Line> {
+        self.find_move();
+        self.lines.clone()
+    }
+
+    /// Set the number of threads used for the search: the main thread and its helpers.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Get the number of probes and hits of the pawn hash tables of all search threads.
+    pub fn pawn_hash_stats(&self) -> PawnHashStats {
+        let mut stats = PawnHashStats::default();
+        for pawn_hash in &self.pawn_hashes {
+            stats += pawn_hash.stats();
+        }
+        stats
+    }
+
+    /// Set the size of the transposition table in megabytes.
+    pub fn set_hash_size(&mut self, megabytes: usize) {
+        match Arc::get_mut(&mut self.game_store) {
+            Some(store) => {
+                if let Err(e) = store.set_size(megabytes) {
+                    error!("Transposition table of {megabytes} MB cannot be allocated: {e}");
+                    if let Err(e) = store.set_size(DEFAULT_HASH_SIZE) {
+                        error!("Transposition table cannot be allocated: {e}");
+                    }
+                }
+            }
+            None => error!("Transposition table is in use and cannot be resized"),
+        }
+    }
+
+    /// Clear the transposition table.
+    pub fn clear_hash(&mut self) {
+        self.game_store.clear();
+    }
+
+    /// Prepare for a new game: start position, empty transposition table and history, counters reset.
+    /// The settings are kept.
+    pub fn new_game(&mut self) {
+        self.set_board(Board::default());
+        self.clear_hash();
+        self.move_number = 0;
+        self.node_count = 0;
+        self.lines.clear();
+    }
+
+    /// Set a receiver for the search progress.
+    pub fn set_reporter(&mut self, reporter: Reporter) {
+        self.reporter = Some(reporter);
     }
 
     /// Find the best move
     pub fn find_move(&mut self) -> Option<Move> {
-        fn stabilise_search_results(
-            old: &[AnnotatedMove],
-            new: &[AnnotatedMove],
-        ) -> Vec<AnnotatedMove> {
-            let len = new.len();
-            let diff_mean: i32 = new
-                .iter()
-                .zip(old.iter())
-                .map(|(new_move, old_move)| old_move.sc - new_move.sc)
-                .sum::<i32>()
-                / len as i32;
-
-            new.iter()
-                .zip(old.iter())
-                .map(|(new_move, old_move)| {
-                    let mut adjusted_move = *new_move;
-                    adjusted_move.sc = (adjusted_move.sc + diff_mean).min(old_move.sc);
-                    adjusted_move
-                })
-                .collect()
-        }
+        self.playing.store(true, Ordering::Relaxed);
+        self.search()
+    }
 
-        fn update_node_count(prior_values: &[AnnotatedMove]) -> u64 {
-            let mut node_count = 0;
-            node_count += prior_values.iter().fold(
-                0,
-                |acc,
-                 AnnotatedMove {
-                     mv: _,
-                     sc: _,
-                     node_count: nc,
-                     ..
-                 }| acc + nc,
-            );
-            node_count
+    /// Find the best move without setting the playing flag first.
+    /// Used when the search runs in the background, so that a stop requested before it has started is not lost.
+    /// In infinite mode or while pondering the result is given not before the search was stopped
+    /// or the pondering has ended.
+    pub fn search(&mut self) -> Option<Move> {
+        let best_move = self.iterative_deepening();
+        while self.playing.load(Ordering::Relaxed)
+            && (self.infinite || self.pondering.load(Ordering::Relaxed))
+        {
+            thread::sleep(Duration::from_millis(WAIT_TIME));
         }
+        best_move
+    }
 
-        let alpha = MIN_INT;
-        let beta = MAX_INT;
+    /// Deepen the search until the move time has elapsed, the maximal depth is reached or a mate is found.
+    fn iterative_deepening(&mut self) -> Option<Move> {
         let mut current_depth: Depth = 0;
         let mut best_move: Option<Move> = None;
-        let mut best_value: MoveScore = MIN_INT;
+        let mut best_value: MoveScore;
         let mut worst_value: MoveScore;
         let mut prior_values = self.board.get_legal_sorted(None);
-        let mut prior_values_old: Vec<AnnotatedMove> = vec![];
-
-        self.set_timer();
+        // Illegal search moves are ignored, if none is left all moves are searched
+        if prior_values
+            .iter()
+            .any(|m| self.search_moves.contains(&m.mv))
+        {
+            prior_values.retain(|m| self.search_moves.contains(&m.mv));
+        }
+        let start = Instant::now();
+        let node_count_start = self.node_count;
+        self.game_store.new_search();
+        let shared_node_count = Arc::new(AtomicU64::new(0));
+        self.lines.clear();
 
-        if prior_values.len() == 1 {
-            return Some(prior_values[0].mv);
+        if prior_values.len() <= 1 {
+            return prior_values.first().map(|m| m.mv);
         }
 
-        while current_depth <= self.max_depth {
-            prior_values.par_iter_mut().for_each(
-                |AnnotatedMove {
-                     mv,
-                     sc,
-                     cp,
-                     node_count,
-                 }| {
-                    let mut b1 = self.board.clone();
-                    let mut pvs = Pvs::new();
-                    pvs.store.h.clone_from(&self.game_store.h);
-                    pvs.history.h.clone_from(&self.game_history.h);
-                    b1.play_unchecked(*mv);
-                    pvs.history.inc(&b1);
-                    *sc = -pvs.execute(&b1, current_depth, -beta, -alpha, &self.playing, *cp);
-                    pvs.history.dec(&b1);
-                    *node_count = pvs.node_count;
-                },
-            );
+        let _timer = if self.infinite {
+            None
+        } else {
+            Some(self.set_timer())
+        };
+        let max_depth = match (self.infinite, self.mate_in) {
+            // A mate in n moves is found after 2n - 1 plies, the root move included
+            (_, Some(n)) => (n.saturating_mul(2) - 2).min(INIT_MAX_DEPTH),
+            (true, None) => INIT_MAX_DEPTH,
+            (false, None) => self.max_depth,
+        };
 
-            if !self.playing.load(Ordering::Relaxed) {
-                info!("Time for this move has expired.");
-                self.node_count += update_node_count(&prior_values);
-                break;
-            }
+        // Every thread keeps its pawn hash table from search to search
+        let mut pawn_hashes = mem::take(&mut self.pawn_hashes).into_iter();
+        let mut pvs = self.new_pvs(&shared_node_count, pawn_hashes.next().unwrap_or_default());
+        let helpers_playing = Arc::new(AtomicBool::new(true));
+        let helper_node_count = Arc::new(AtomicU64::new(0));
+        // A node limit is only reproducible with a single thread
+        let helpers = if self.max_nodes.is_some() {
+            0
+        } else {
+            self.threads - 1
+        };
+        let helper_handles: Vec<_> = (1..=helpers)
+            .map(|id| {
+                let mut helper =
+                    self.new_pvs(&shared_node_count, pawn_hashes.next().unwrap_or_default());
+                let board = self.board.clone();
+                let mut root_moves = prior_values.clone();
+                let playing = helpers_playing.clone();
+                let node_count = helper_node_count.clone();
+                thread::spawn(move || {
+                    // Helpers search at staggered depths, filling the transposition table
+                    let mut depth = (id % 2) as Depth;
+                    while depth <= max_depth && playing.load(Ordering::Relaxed) {
+                        let node_count_start = helper.node_count;
+                        helper.execute_root(
+                            &board,
+                            &mut root_moves,
+                            depth,
+                            1,
+                            (MIN_INT, MAX_INT),
+                            &playing,
+                        );
+                        root_moves.sort_by_key(|m| Reverse(m.sc));
+                        node_count
+                            .fetch_add(helper.node_count - node_count_start, Ordering::Relaxed);
+                        depth += 1;
+                    }
+                    helper.pawn_hash
+                })
+            })
+            .collect();
+
+        let mut score: MoveScore = 0;
+        while current_depth <= max_depth {
+            // Search within a window around the score of the last iteration, widened on failure
+            let mut delta = ASPIRATION_WINDOW;
+            let (mut alpha, mut beta) = if current_depth >= ASPIRATION_DEPTH_MIN
+                && self.multi_pv <= 1
+                && self.mate_in.is_none()
+                && score.abs() < MATE_LEVEL
+            {
+                (score - delta, score + delta)
+            } else {
+                (MIN_INT, MAX_INT)
+            };
+            loop {
+                let node_count_iteration = pvs.node_count;
+                pvs.execute_root(
+                    &self.board,
+                    &mut prior_values,
+                    current_depth,
+                    self.multi_pv,
+                    (alpha, beta),
+                    &self.playing,
+                );
+                self.node_count += pvs.node_count - node_count_iteration;
+                if !self.playing.load(Ordering::Relaxed) {
+                    break;
+                }
 
-            if current_depth % 2 == 1 {
-                prior_values = stabilise_search_results(&prior_values_old, &prior_values);
+                let best = *prior_values.iter().max_by_key(|m| m.sc).unwrap();
+                score = best.sc;
+                let bound = Bound::new(score, alpha, beta);
+                match bound {
+                    Bound::Exact => break,
+                    Bound::Upper => {
+                        beta = (alpha + beta) / 2;
+                        alpha = (score - delta).max(MIN_INT);
+                    }
+                    Bound::Lower => beta = (score + delta).min(MAX_INT),
+                }
+                delta *= 2;
+                info!(
+                    "Aspiration window failed at depth {current_depth}, new window: {alpha} {beta}"
+                );
+                let nodes =
+                    self.node_count - node_count_start + helper_node_count.load(Ordering::Relaxed);
+                let lines = [self.line(&best, current_depth)];
+                self.report(
+                    &lines,
+                    bound,
+                    current_depth,
+                    pvs.sel_depth + 1,
+                    nodes,
+                    start,
+                );
             }
 
-            prior_values.sort_by(|a, b| b.sc.cmp(&a.sc));
+            if !self.playing.load(Ordering::Relaxed) {
+                info!("Time or nodes for this move have been used up.");
+                break;
+            }
 
+            // Only the best scores are exact, the others are bounds found by null window searches
+            prior_values.sort_by_key(|m| Reverse(m.sc));
             best_move = Some(prior_values[0].mv);
             best_value = prior_values[0].sc;
+            self.lines = prior_values
+                .iter()
+                .take(self.multi_pv.max(1))
+                .map(|m| self.line(m, current_depth))
+                .collect();
+            let nodes =
+                self.node_count - node_count_start + helper_node_count.load(Ordering::Relaxed);
+            self.report(
+                &self.lines,
+                Bound::Exact,
+                current_depth,
+                pvs.sel_depth + 1,
+                nodes,
+                start,
+            );
+
             if best_value > MATE_LEVEL {
                 info!(
                     "Mate level was reached. Best move was {}",
@@ -161,7 +396,6 @@ impl Game {
                 );
                 break;
             }
-            self.node_count += update_node_count(&prior_values);
             info!(
                 "Depth: {} Nodes examined: {}",
                 current_depth, self.node_count
@@ -176,30 +410,114 @@ impl Game {
                     .join(", ")
             );
 
-            // Forward pruning
-            if current_depth >= FORWARD_PRUNING_DEPTH_START {
+            // Forward pruning, but not in mate search where every move has to be examined
+            if current_depth >= self.forward_pruning_depth_start && self.mate_in.is_none() {
                 let moves_count = prior_values.len();
 
                 worst_value = prior_values[moves_count - 1].sc;
                 if worst_value < best_value {
-                    let cut_index =
-                        max(FORWARD_PRUNING_MINIMUM, moves_count / FORWARD_PRUNING_RATIO);
+                    let cut_index = max(
+                        max(self.forward_pruning_minimum, self.multi_pv),
+                        moves_count / self.forward_pruning_ratio,
+                    );
+                    // The moves to keep are chosen by exact scores, so the failed low ones are searched again
+                    let exact = self.multi_pv.max(1);
+                    if exact < cut_index && cut_index < moves_count {
+                        let node_count_research = pvs.node_count;
+                        pvs.execute_root(
+                            &self.board,
+                            &mut prior_values[exact..],
+                            current_depth,
+                            cut_index - exact,
+                            (MIN_INT, MAX_INT),
+                            &self.playing,
+                        );
+                        self.node_count += pvs.node_count - node_count_research;
+                        if !self.playing.load(Ordering::Relaxed) {
+                            info!("Time or nodes for this move have been used up.");
+                            break;
+                        }
+                        prior_values[exact..].sort_by_key(|m| Reverse(m.sc));
+                    }
                     info!("cut at {cut_index}");
                     prior_values.truncate(cut_index);
                 }
             }
 
             current_depth += 1;
-            prior_values_old = prior_values.clone();
         }
-        self.game_store.put(
-            current_depth - 1,
-            best_value,
-            &self.board,
-            &best_move.unwrap(),
-        );
 
-        best_move
+        helpers_playing.store(false, Ordering::Relaxed);
+        self.pawn_hashes.push(pvs.pawn_hash);
+        for handle in helper_handles {
+            match handle.join() {
+                Ok(pawn_hash) => self.pawn_hashes.push(pawn_hash),
+                Err(_) => error!("Helper search aborted"),
+            }
+        }
+        self.node_count += helper_node_count.load(Ordering::Relaxed);
+
+        // Without a completed iteration, the first move of the ordering is played
+        best_move.or(Some(prior_values[0].mv))
+    }
+
+    /// Create a search sharing the transposition table and the node counter, using the given pawn hash table.
+    fn new_pvs(&self, shared_node_count: &Arc<AtomicU64>, pawn_hash: PawnHash) -> Pvs {
+        let mut pvs = Pvs::new(self.game_store.clone());
+        pvs.pawn_hash = pawn_hash;
+        pvs.shared_node_count = shared_node_count.clone();
+        pvs.max_nodes = self.max_nodes;
+        pvs.mate_search = self.mate_in.is_some();
+        pvs.null_move_pruning = self.null_move_pruning;
+        pvs.history.h.clone_from(&self.game_history.h);
+        pvs
+    }
+
+    /// Get the line of a root move searched to the given depth, its principal variation taken from the
+    /// transposition table. A mating line is given up to the mate, which extensions may put beyond the depth.
+    fn line(&self, root: &AnnotatedMove, depth: Depth) -> Line {
+        let mut b1 = self.board.clone();
+        b1.play_unchecked(root.mv);
+        let score = Score::new(root.sc, depth);
+        let len = match score {
+            Score::Mate(n) if n > 0 => 2 * n as usize - 2,
+            Score::Mate(n) => 2 * n.unsigned_abs() as usize - 1,
+            Score::Cp(_) => depth as usize,
+        };
+        let mut pv = vec![root.mv];
+        pv.append(&mut self.game_store.pv(&b1, len));
+        Line { score, pv }
+    }
+
+    /// Report the search progress of the given lines.
+    fn report(
+        &self,
+        lines: &[Line],
+        bound: Bound,
+        depth: Depth,
+        sel_depth: Depth,
+        nodes: u64,
+        start: Instant,
+    ) {
+        if let Some(reporter) = &self.reporter {
+            let time = start.elapsed().as_millis() as MoveTime;
+            for (i, line) in lines.iter().enumerate() {
+                let info = SearchInfo {
+                    depth: depth + 1,
+                    sel_depth,
+                    multi_pv: i + 1,
+                    score: line.score,
+                    bound,
+                    nodes,
+                    nps: nodes * 1000 / max(time, 1),
+                    time,
+                    hashfull: self.game_store.hashfull(),
+                    curr_move: line.pv.first().copied(),
+                    pv: line.pv.clone(),
+                };
+                reporter(&self.board, &info);
+            }
+        }
     }
 }
 
@@ -208,3 +526,249 @@ impl Default for Game {
         Game::new(String::from(""), 0, 0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_stopped() {
+        let mut g = Game::new("".to_string(), 0, 60_000);
+        let start = Instant::now();
+        g.playing().store(false, Ordering::Relaxed);
+        let mv = g.search().unwrap();
+        assert!(g.board.is_legal(mv));
+        assert!(start.elapsed() < Duration::from_millis(1000));
+
+        let mut g = Game::new("".to_string(), 2, 60_000);
+        let mv = g.find_move().unwrap();
+        assert!(g.board.is_legal(mv));
+        assert!(g.playing().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_search_infinite() {
+        let mut g = Game::new("".to_string(), 1, 10);
+        g.infinite = true;
+        let playing = g.playing();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            playing.store(false, Ordering::Relaxed);
+        });
+        let start = Instant::now();
+        let mv = g.find_move().unwrap();
+        assert!(g.board.is_legal(mv));
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        stopper.join().unwrap();
+    }
+
+    #[test]
+    fn test_search_ponder() {
+        let mut g = Game::new("".to_string(), 3, 10);
+        g.pondering().store(true, Ordering::Relaxed);
+        let pondering = g.pondering();
+        let ponderhit = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            pondering.store(false, Ordering::Relaxed);
+        });
+        let start = Instant::now();
+        let mv = g.find_move().unwrap();
+        assert!(g.board.is_legal(mv));
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        ponderhit.join().unwrap();
+
+        let mut b = g.board.clone();
+        b.play_unchecked(mv);
+        assert!(b.is_legal(g.ponder_move().unwrap()));
+    }
+
+    #[test]
+    fn test_search_nodes() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut results = vec![];
+        for _ in 0..2 {
+            let mut g = Game::new(fen.to_string(), 0, 60_000);
+            g.max_nodes = Some(50_000);
+            let start = Instant::now();
+            let mv = g.find_move().unwrap();
+            assert!(start.elapsed() < Duration::from_millis(30_000));
+            results.push((mv, g.lines().to_vec()));
+        }
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn test_search_threads() {
+        let fen = "r1b2k1r/pppq3p/2np1p2/8/2B2B2/8/PPP3PP/4RR1K w - - 0 1";
+        let mut g = Game::new(fen.to_string(), 0, 60_000);
+        g.set_threads(3);
+        g.mate_in = Some(2);
+        assert_eq!(g.find_move().unwrap().to_string(), "f4h6");
+        assert_eq!(g.score(), Some(Score::Mate(2)));
+
+        // every thread keeps its pawn hash table for the next search
+        assert_eq!(g.pawn_hashes.len(), 3);
+        let stats = g.pawn_hash_stats();
+        g.find_move();
+        assert!(g.pawn_hash_stats().hits > stats.hits);
+    }
+
+    #[test]
+    fn test_search_aspiration() {
+        let infos = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let infos_clone = infos.clone();
+        let fen = "5rk1/1p6/q2P3p/2p2rp1/p1nbQ3/P1N3BP/1PR1B1P1/4K3 b - - 0 1";
+        let mut g = Game::new(fen.to_string(), 6, 60_000);
+        g.set_threads(1);
+        g.set_reporter(Box::new(move |_, info: &SearchInfo| {
+            infos_clone.lock().unwrap().push(info.clone())
+        }));
+        let mv = g.find_move().unwrap();
+
+        // the window fails on the way, but every iteration ends with an exact score
+        let infos = infos.lock().unwrap();
+        assert!(infos.iter().any(|i| i.bound != Bound::Exact));
+        for depth in 1..=7 {
+            let last = infos.iter().rfind(|i| i.depth == depth).unwrap();
+            assert_eq!(last.bound, Bound::Exact);
+        }
+        let last = infos.last().unwrap();
+        assert_eq!(last.pv[0], mv);
+        assert_eq!(Some(last.score), g.score());
+    }
+
+    #[test]
+    fn test_search_mate_score() {
+        // mate scores are given as moves from the root, even if the line was extended
+        let fen = "r1b2k1r/pppq3p/2np1p2/8/2B2B2/8/PPP3PP/4RR1K w - - 0 1";
+        let mut g = Game::new(fen.to_string(), 5, 60_000);
+        assert_eq!(g.find_move().unwrap().to_string(), "f4h6");
+        assert_eq!(g.score(), Some(Score::Mate(2)));
+    }
+
+    #[test]
+    fn test_search_moves() {
+        let mut g = Game::new("".to_string(), 3, 60_000);
+        g.search_moves = vec![
+            Move::from_str("a2a3").unwrap(),
+            Move::from_str("h2h4").unwrap(),
+        ];
+        let mv = g.find_move().unwrap();
+        assert!(g.search_moves.contains(&mv));
+
+        g.search_moves = vec![Move::from_str("g1f3").unwrap()];
+        assert_eq!(g.find_move().unwrap().to_string(), "g1f3");
+
+        g.search_moves = vec![
+            Move::from_str("a1a2").unwrap(),
+            Move::from_str("b1c3").unwrap(),
+        ];
+        assert_eq!(g.find_move().unwrap().to_string(), "b1c3");
+
+        // without a legal search move all moves are searched
+        g.search_moves = vec![Move::from_str("a1a2").unwrap()];
+        let mv = g.find_move().unwrap();
+        assert!(g.board.is_legal(mv));
+    }
+
+    #[test]
+    fn test_new_game() {
+        let mut g = Game::new(
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2".to_string(),
+            2,
+            60_000,
+        );
+        g.multi_pv = 3;
+        g.move_number = 2;
+        g.game_history.inc(&g.board);
+        g.find_move();
+        let mut board = g.board.clone();
+        board.play_unchecked(g.pv()[0]);
+        assert!(g.game_store.get(0, &board).is_some());
+        assert!(g.node_count > 0);
+
+        g.new_game();
+        assert_eq!(g.board, Board::default());
+        assert!(g.game_store.get(0, &board).is_none());
+        assert!(g.game_history.h.is_empty());
+        assert_eq!(g.move_number, 0);
+        assert_eq!(g.node_count, 0);
+        assert!(g.pv().is_empty());
+        assert!(g.lines().is_empty());
+        assert_eq!(g.multi_pv, 3);
+    }
+
+    #[test]
+    fn test_find_lines() {
+        let mut g = Game::new("".to_string(), 3, 60_000);
+        g.multi_pv = 3;
+        let lines = g.find_lines();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(g.pv(), lines[0].pv.as_slice());
+        for pair in lines.windows(2) {
+            assert_ne!(pair[0].pv[0], pair[1].pv[0]);
+            match (pair[0].score, pair[1].score) {
+                (Score::Cp(a), Score::Cp(b)) => assert!(a >= b),
+                _ => panic!("no mate expected"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_reported_score() {
+        // the searched score is reported, a mate found at an odd depth is not held back
+        // by the score of the iteration before
+        let fen = "1rb4r/pkPp3p/1b1P3n/1Q6/N3Pp2/8/P1P3PP/7K w - - 0 1";
+        let mut g = Game::new(fen.to_string(), 1, 60_000);
+        g.set_threads(1);
+        assert_eq!(g.find_move().unwrap().to_string(), "b5d5");
+        assert_eq!(g.score(), Some(Score::Mate(2)));
+    }
+
+    #[test]
+    fn test_find_lines_scores() {
+        // the score of every line is the one found by a search of its move alone
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 1";
+        let depth = 5;
+        let mut g = Game::new(fen.to_string(), depth, 60_000);
+        g.set_threads(1);
+        g.multi_pv = 4;
+        let lines = g.find_lines();
+        assert_eq!(lines.len(), 4);
+
+        let playing = Arc::new(AtomicBool::new(true));
+        for line in lines {
+            let mut pvs = Pvs::new(g.game_store.clone());
+            let mut b1 = g.board.clone();
+            b1.play_unchecked(line.pv[0]);
+            pvs.history.inc(&b1);
+            pvs.previous_move = Some(line.pv[0]);
+            let capture = g.board.color_on(line.pv[0].to).is_some();
+            let value = -pvs.execute(&b1, depth, MIN_INT, MAX_INT, &playing, capture);
+            assert_eq!(line.score, Score::new(value, depth));
+        }
+    }
+
+    #[test]
+    fn test_forward_pruning() {
+        // the moves kept are the best by their exact scores, so the best move is not pruned
+        let fen = "r1bq1rk1/pp2ppbp/2np2p1/2n5/P3PP2/N1P2N2/1PB3PP/R1B1QRK1 b - - 0 1";
+        let mut pruned = Game::new(fen.to_string(), 7, 600_000);
+        pruned.set_threads(1);
+        let mut full = Game::new(fen.to_string(), 7, 600_000);
+        full.set_threads(1);
+        full.forward_pruning_depth_start = INIT_MAX_DEPTH;
+        assert_eq!(pruned.find_move(), full.find_move());
+        assert_eq!(pruned.score(), full.score());
+    }
+
+    #[test]
+    fn test_search_no_moves() {
+        let mut g = Game::new(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".to_string(),
+            0,
+            1000,
+        );
+        assert!(g.find_move().is_none());
+    }
+}
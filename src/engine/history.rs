@@ -52,6 +52,12 @@ impl History {
     }
 }
 
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,9 +96,3 @@ mod tests {
         assert_eq!(result, 2);
     }
 }
-
-impl Default for History {
-    fn default() -> Self {
-        Self::new()
-    }
-}
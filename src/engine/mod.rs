@@ -10,8 +10,17 @@ pub mod history;
 /// Move generator
 pub mod move_gen;
 
+/// Move ordering heuristics
+pub mod move_order;
+
 /// Principal variant search
 pub mod pvs;
 
+/// Search progress reports
+pub mod report;
+
+/// Static exchange evaluation
+pub mod see;
+
 /// Transposition table
 pub mod store;
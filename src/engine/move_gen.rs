@@ -1,5 +1,6 @@
+use super::{constants::PIECE_VALUES, see::StaticExchange};
 use crate::misc::types::*;
-use cozy_chess::{Board, Move};
+use cozy_chess::{Board, Move, Piece, Rank, Square};
 use std::ops::Not;
 
 /// A trait to extend the move generator of crate Cozy Chess.
@@ -8,30 +9,40 @@ pub trait MoveGenPrime {
 }
 
 impl MoveGenPrime for Board {
-    /// Get all legal moves for given position, sort captures first.
-    /// En passant captures are not considered.
+    /// Get all legal moves for given position.
+    /// Captures not losing material come first, ordered by most valuable victim and least valuable
+    /// attacker, then the quiet moves, then the losing captures. Captures are scored by their
+    /// static exchange evaluation. En passant captures count as captures.
     /// Also takes a proven good move ("old move") to be sorted first.
     fn get_legal_sorted(&self, old_move: Option<Move>) -> Vec<AnnotatedMove> {
-        let mut result: Vec<AnnotatedMove> = Vec::new();
+        let mut captures: Vec<AnnotatedMove> = Vec::new();
+        let mut others: Vec<AnnotatedMove> = Vec::new();
         let enemy_pieces = self.colors(!self.side_to_move());
-        let other_squares = enemy_pieces.not();
+        let pawn_targets = self.en_passant().map_or(enemy_pieces, |file| {
+            let rank = Rank::Sixth.relative_to(self.side_to_move());
+            enemy_pieces | Square::new(file, rank).bitboard()
+        });
 
         self.generate_moves(|moves| {
-            let mut captures = moves;
-            captures.to &= enemy_pieces;
-            for mv in captures {
-                result.push(AnnotatedMove {
+            let targets = match moves.piece {
+                Piece::Pawn => pawn_targets,
+                _ => enemy_pieces,
+            };
+            let mut capture_moves = moves;
+            capture_moves.to &= targets;
+            for mv in capture_moves {
+                captures.push(AnnotatedMove {
                     mv,
-                    sc: 0,
+                    sc: self.see(mv),
                     node_count: 0,
                     cp: true,
                 });
             }
 
-            let mut others = moves;
-            others.to &= other_squares;
-            for mv in others {
-                result.push(AnnotatedMove {
+            let mut other_moves = moves;
+            other_moves.to &= targets.not();
+            for mv in other_moves {
+                others.push(AnnotatedMove {
                     mv,
                     sc: 0,
                     node_count: 0,
@@ -42,6 +53,31 @@ impl MoveGenPrime for Board {
             false
         });
 
+        captures.sort_by_key(|c| {
+            // the target square of an en passant capture is empty
+            let victim = self
+                .piece_on(c.mv.to)
+                .map_or(PIECE_VALUES[Piece::Pawn as usize], |p| {
+                    PIECE_VALUES[p as usize]
+                });
+            let attacker = self
+                .piece_on(c.mv.from)
+                .map_or(0, |p| PIECE_VALUES[p as usize]);
+            if c.sc >= 0 {
+                (0, -victim, attacker)
+            } else {
+                (1, -c.sc, 0)
+            }
+        });
+        let losing = captures
+            .iter()
+            .position(|c| c.sc < 0)
+            .unwrap_or(captures.len());
+        let mut losing_captures = captures.split_off(losing);
+        let mut result = captures;
+        result.append(&mut others);
+        result.append(&mut losing_captures);
+
         if let Some(mv) = old_move {
             for (i, c) in (&mut result.iter()).enumerate() {
                 if c.mv == mv {
@@ -90,8 +126,8 @@ mod tests {
 
     #[test]
     fn test_get_legal_sorted() {
-        for i in 0..TEST_RESOURCE.len() {
-            let (fen, bestmoves, _) = parse_epd(TEST_RESOURCE[i].to_string());
+        for epd in TEST_RESOURCE {
+            let (fen, bestmoves, _) = parse_epd(epd.to_string());
             let first_move = bestmoves[0].parse::<Move>().unwrap();
             let board = Board::from_str(fen.as_str()).unwrap();
             let mut moves_length = 0;
@@ -104,4 +140,27 @@ mod tests {
             assert_eq!(moves_sorted[0].mv, first_move);
         }
     }
+
+    #[test]
+    fn test_captures_sorted() {
+        let board = Board::from_str("4k3/8/2p5/1q1n4/2P5/5B2/8/Q3K3 w - - 0 1").unwrap();
+        let moves_sorted = board.get_legal_sorted(None);
+        let moves: Vec<String> = moves_sorted.iter().map(|m| m.mv.to_string()).collect();
+
+        // the pawn takes the queen before the knight, the bishop takes the defended knight last
+        assert_eq!(moves[0], "c4b5");
+        assert_eq!(moves[1], "c4d5");
+        assert_eq!(moves.last().unwrap(), "f3d5");
+        assert!(moves_sorted.last().unwrap().sc < 0);
+    }
+
+    #[test]
+    fn test_en_passant_sorted() {
+        let board = Board::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let moves_sorted = board.get_legal_sorted(None);
+        assert_eq!(moves_sorted[0].mv.to_string(), "e5d6");
+        assert!(moves_sorted[0].cp);
+        assert_eq!(moves_sorted[0].sc, PIECE_VALUES[Piece::Pawn as usize]);
+        assert!(moves_sorted[1..].iter().all(|m| !m.cp));
+    }
 }
@@ -0,0 +1,218 @@
+use super::constants::*;
+use crate::misc::types::*;
+use cozy_chess::{Board, Move};
+
+/// Heuristics learned from beta cutoffs to order quiet moves:
+/// killer moves per ply, a butterfly history and countermoves.
+pub struct MoveOrder {
+    killers: Vec<[Option<Move>; 2]>,
+    history: Box<[[[MoveScore; 64]; 64]; 2]>,
+    counters: Box<[[[Option<Move>; 64]; 6]; 2]>,
+}
+
+impl MoveOrder {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            killers: Vec::new(),
+            history: Box::new([[[0; 64]; 64]; 2]),
+            counters: Box::new([[[None; 64]; 6]; 2]),
+        }
+    }
+
+    /// Get the killer moves at the given ply.
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers.get(ply).copied().unwrap_or_default()
+    }
+
+    /// Get the history score of a quiet move on the given board.
+    pub fn history(&self, board: &Board, mv: Move) -> MoveScore {
+        self.history[board.side_to_move() as usize][mv.from as usize][mv.to as usize]
+    }
+
+    /// Get the move which refuted the previous move last time.
+    pub fn counter(&self, board: &Board, previous_move: Option<Move>) -> Option<Move> {
+        let previous_move = previous_move?;
+        let piece = board.piece_on(previous_move.to)?;
+        self.counters[!board.side_to_move() as usize][piece as usize][previous_move.to as usize]
+    }
+
+    /// Learn from a quiet move causing a beta cutoff at the given ply and depth.
+    /// The quiet moves tried before are penalised in the history.
+    pub fn update(
+        &mut self,
+        board: &Board,
+        ply: usize,
+        depth: Depth,
+        mv: Move,
+        previous_move: Option<Move>,
+        quiets_tried: &[Move],
+    ) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        let bonus = MoveScore::from(depth) * MoveScore::from(depth);
+        self.add_history(board, mv, bonus);
+        for &quiet in quiets_tried {
+            self.add_history(board, quiet, -bonus);
+        }
+
+        if let Some(previous_move) = previous_move {
+            if let Some(piece) = board.piece_on(previous_move.to) {
+                self.counters[!board.side_to_move() as usize][piece as usize]
+                    [previous_move.to as usize] = Some(mv);
+            }
+        }
+    }
+
+    /// Change a history score, keeping it within the limits.
+    fn add_history(&mut self, board: &Board, mv: Move, bonus: MoveScore) {
+        let entry =
+            &mut self.history[board.side_to_move() as usize][mv.from as usize][mv.to as usize];
+        let bonus = bonus.clamp(-HISTORY_MAX, HISTORY_MAX);
+        *entry += bonus - *entry * bonus.abs() / HISTORY_MAX;
+    }
+}
+
+impl Default for MoveOrder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hands out the moves of a position in stages: the hash move, the captures not losing material,
+/// the quiet moves ordered by promotion, killers, countermove and history, the losing captures.
+/// The quiet moves are only ordered once they are needed.
+pub struct MovePicker {
+    moves: Vec<AnnotatedMove>,
+    index: usize,
+    hash_move: Option<Move>,
+    hash_move_done: bool,
+    quiets_sorted: bool,
+    killers: [Option<Move>; 2],
+    counter: Option<Move>,
+}
+
+impl MovePicker {
+    /// Constructor, taking the moves as sorted by `MoveGenPrime::get_legal_sorted`.
+    pub fn new(
+        moves: Vec<AnnotatedMove>,
+        hash_move: Option<Move>,
+        killers: [Option<Move>; 2],
+        counter: Option<Move>,
+    ) -> Self {
+        let hash_move = hash_move.filter(|h| moves.iter().any(|m| m.mv == *h));
+        Self {
+            moves,
+            index: 0,
+            hash_move,
+            hash_move_done: false,
+            quiets_sorted: false,
+            killers,
+            counter,
+        }
+    }
+
+    /// Get the next move to search, or None if all moves were handed out.
+    pub fn pick(&mut self, board: &Board, order: &MoveOrder) -> Option<AnnotatedMove> {
+        if !self.hash_move_done {
+            self.hash_move_done = true;
+            if let Some(hash_move) = self.hash_move {
+                return self.moves.iter().find(|m| m.mv == hash_move).copied();
+            }
+        }
+
+        loop {
+            let child = *self.moves.get(self.index)?;
+            if !child.cp && !self.quiets_sorted {
+                self.sort_quiets(board, order);
+                continue;
+            }
+            self.index += 1;
+            if Some(child.mv) != self.hash_move {
+                return Some(child);
+            }
+        }
+    }
+
+    /// Order the quiet moves, starting at the current index.
+    fn sort_quiets(&mut self, board: &Board, order: &MoveOrder) {
+        self.quiets_sorted = true;
+        let end = self.moves[self.index..]
+            .iter()
+            .position(|m| m.cp)
+            .map_or(self.moves.len(), |i| self.index + i);
+        let quiets = &mut self.moves[self.index..end];
+        for quiet in quiets.iter_mut() {
+            quiet.sc = if quiet.mv.promotion.is_some() {
+                HISTORY_MAX * 4
+            } else if Some(quiet.mv) == self.killers[0] {
+                HISTORY_MAX * 3
+            } else if Some(quiet.mv) == self.killers[1] {
+                HISTORY_MAX * 2
+            } else if Some(quiet.mv) == self.counter {
+                HISTORY_MAX + 1
+            } else {
+                order.history(board, quiet.mv)
+            };
+        }
+        quiets.sort_by_key(|m| -m.sc);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::move_gen::MoveGenPrime;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_move_picker() {
+        let board = Board::from_str("4k3/8/2p5/3n4/8/5B2/8/4K2R w K - 0 1").unwrap();
+        let moves = board.get_legal_sorted(None);
+        let count = moves.len();
+        let killer: Move = "h1h7".parse().unwrap();
+        let counter: Move = "e1d2".parse().unwrap();
+        let hash_move: Move = "f3e4".parse().unwrap();
+
+        let mut order = MoveOrder::new();
+        order.update(&board, 3, 5, killer, None, &[counter]);
+        assert_eq!(order.killers(3), [Some(killer), None]);
+        assert_eq!(order.killers(2), [None, None]);
+        assert_eq!(order.history(&board, killer), 25);
+        assert_eq!(order.history(&board, counter), -25);
+
+        let mut picker = MovePicker::new(moves, Some(hash_move), order.killers(3), Some(counter));
+        let mut picked = vec![];
+        while let Some(m) = picker.pick(&board, &order) {
+            picked.push(m.mv.to_string());
+        }
+
+        assert_eq!(picked.len(), count);
+        assert_eq!(picked[0], "f3e4");
+        assert_eq!(picked[1], "h1h7");
+        assert_eq!(picked[2], "e1d2");
+        // the only capture loses material and comes last
+        assert_eq!(picked[count - 1], "f3d5");
+    }
+
+    #[test]
+    fn test_counter() {
+        let mut board = Board::default();
+        let previous_move: Move = "e2e4".parse().unwrap();
+        board.play(previous_move);
+        let reply: Move = "c7c5".parse().unwrap();
+
+        let mut order = MoveOrder::new();
+        assert_eq!(order.counter(&board, Some(previous_move)), None);
+        order.update(&board, 0, 1, reply, Some(previous_move), &[]);
+        assert_eq!(order.counter(&board, Some(previous_move)), Some(reply));
+        assert_eq!(order.counter(&board, None), None);
+    }
+}
@@ -1,27 +1,111 @@
 use super::constants::MIN_INT;
-use super::{constants::*, history::History, move_gen::MoveGenPrime, store::Store};
-use crate::eval::{evaluation::Evaluation, simple::Simple};
+use super::{
+    constants::*,
+    history::History,
+    move_gen::MoveGenPrime,
+    move_order::{MoveOrder, MovePicker},
+    store::{Bound, Store},
+};
+use crate::eval::{pawn_hash::PawnHash, tapered::Tapered};
 use crate::misc::types::*;
-use cozy_chess::{Board, GameStatus, Move};
+use cozy_chess::{Board, GameStatus, Move, Piece, Rank};
+use std::cmp::Reverse;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, OnceLock,
 };
 
 /// A principal variant search.
 pub struct Pvs {
     pub history: History,
     pub node_count: u64,
-    pub store: Store,
+    pub shared_node_count: Arc<AtomicU64>,
+    pub max_nodes: Option<u64>,
+    pub sel_depth: Depth,
+    pub mate_search: bool,
+    pub null_move_pruning: bool,
+    pub store: Arc<Store>,
+    pub move_order: MoveOrder,
+    pub previous_move: Option<Move>,
+    pub pawn_hash: PawnHash,
+    ply: Depth,
+    extensions: Depth,
+    excluded_move: Option<Move>,
 }
 
 impl Pvs {
-    /// Constructor
-    pub fn new() -> Self {
+    /// Constructor, taking the transposition table shared by all searches.
+    pub fn new(store: Arc<Store>) -> Self {
         Self {
             history: History::new(),
             node_count: 0,
-            store: Store::new(),
+            shared_node_count: Arc::new(AtomicU64::new(0)),
+            max_nodes: None,
+            sel_depth: 0,
+            mate_search: false,
+            null_move_pruning: true,
+            store,
+            move_order: MoveOrder::new(),
+            previous_move: None,
+            pawn_hash: PawnHash::default(),
+            ply: 0,
+            extensions: 0,
+            excluded_move: None,
+        }
+    }
+
+    /// Search the root moves of the given board to the given depth, not counting the root move.
+    /// The scores of the best `multi_pv` moves are exact within the window of Alpha and Beta,
+    /// the other moves are searched with a null window only, so that their scores are upper bounds.
+    /// A move failing high is moved to the front and the search stops, the scores of the moves
+    /// not yet searched are left as they are.
+    pub fn execute_root(
+        &mut self,
+        board: &Board,
+        root_moves: &mut [AnnotatedMove],
+        depth: Depth,
+        multi_pv: usize,
+        (mut alpha, beta): (MoveScore, MoveScore),
+        playing: &Arc<AtomicBool>,
+    ) {
+        let multi_pv = multi_pv.max(1);
+        let mut best_values: Vec<MoveScore> = Vec::new();
+
+        for (i, root) in root_moves.iter_mut().enumerate() {
+            let node_count_start = self.node_count;
+            let mut b1 = board.clone();
+            b1.play_unchecked(root.mv);
+            self.history.inc(&b1);
+            self.previous_move = Some(root.mv);
+
+            root.sc = if i < multi_pv {
+                -self.execute(&b1, depth, -beta, -alpha, playing, root.cp)
+            } else {
+                let value = -self.execute(&b1, depth, -alpha - 1, -alpha, playing, root.cp);
+                if value > alpha {
+                    -self.execute(&b1, depth, -beta, -alpha, playing, root.cp)
+                } else {
+                    value
+                }
+            };
+
+            self.history.dec(&b1);
+            root.node_count = self.node_count - node_count_start;
+            if !playing.load(Ordering::Relaxed) {
+                return;
+            }
+            if root.sc >= beta {
+                root_moves[..=i].rotate_right(1);
+                return;
+            }
+
+            // Alpha is the worst score of the best lines
+            best_values.push(root.sc);
+            best_values.sort_by_key(|v| Reverse(*v));
+            best_values.truncate(multi_pv);
+            if best_values.len() == multi_pv {
+                alpha = alpha.max(best_values[multi_pv - 1]);
+            }
         }
     }
 
@@ -33,18 +117,22 @@ impl Pvs {
         mut alpha: MoveScore,
         beta: MoveScore,
         playing: &Arc<AtomicBool>,
-        _capture: bool,
+        capture: bool,
     ) -> MoveScore {
         let mut best_move: Option<Move> = None;
         let mut best_value: MoveScore = MIN_INT;
+        let excluded_move = self.excluded_move.take();
 
         if !playing.load(Ordering::Relaxed) {
             return 0;
         }
 
+        self.sel_depth = self.sel_depth.max(self.ply);
+
         if board.status() != GameStatus::Ongoing {
             if board.status() == GameStatus::Won {
-                return -MATE - i32::from(depth);
+                // Mate scores count the plies from the root, the extensions of the line not included
+                return -MATE - i32::from(depth - self.extensions);
             }
             return 0;
         }
@@ -54,32 +142,105 @@ impl Pvs {
         }
 
         if depth < 1 {
-            self.node_count += 1;
-            return Simple::evaluate(board);
+            return self.quiescence(board, alpha, beta, 0, playing);
         }
 
-        let children: Vec<AnnotatedMove> = match self.store.get(depth, board) {
-            Some((_, v, true)) => return v,
-            Some((mv, _, false)) => board.get_legal_sorted(Some(mv)),
-            None => board.get_legal_sorted(None),
+        // Stored scores cut off null window searches only, so that the principal variation is searched.
+        let pv_node = beta - alpha > 1;
+        let entry = self
+            .store
+            .get(depth, board)
+            .map(|(mv, v, bound, d)| (mv, shift_mate(v, -self.extensions), bound, d));
+        let hash_move = match entry {
+            Some((_, v, bound, d))
+                if d >= depth
+                    && !pv_node
+                    && excluded_move.is_none()
+                    && bound.permits_cutoff(v, alpha, beta) =>
+            {
+                return v
+            }
+            Some((mv, ..)) => Some(mv),
+            None => None,
         };
+        let alpha_orig = alpha;
 
-        for (i, child) in children.iter().enumerate() {
+        let previous_move = self.previous_move;
+        if !pv_node && excluded_move.is_none() && self.null_move_cutoff(board, depth, beta, playing)
+        {
+            return beta;
+        }
+
+        let singular = match entry {
+            Some(entry) if excluded_move.is_none() => {
+                self.singular(board, depth, entry, capture, playing)
+            }
+            _ => false,
+        };
+        self.previous_move = previous_move;
+
+        let ply = self.ply as usize;
+        let killers = self.move_order.killers(ply);
+        let in_check = !board.checkers().is_empty();
+        let mut picker = MovePicker::new(
+            board.get_legal_sorted(None),
+            hash_move,
+            killers,
+            self.move_order.counter(board, previous_move),
+        );
+        let mut quiets_tried: Vec<Move> = Vec::new();
+        let mut i: usize = 0;
+
+        self.ply += 1;
+        while let Some(child) = picker.pick(board, &self.move_order) {
+            if Some(child.mv) == excluded_move {
+                continue;
+            }
             let mut b1 = board.clone();
             b1.play_unchecked(child.mv);
             self.history.inc(&b1);
+            self.previous_move = Some(child.mv);
+
+            let extension = if self.extensions >= EXTENSIONS_MAX {
+                0
+            } else if singular && Some(child.mv) == hash_move {
+                1
+            } else {
+                let recapture = capture && previous_move.is_some_and(|m| m.to == child.mv.to);
+                extension(board, &b1, &child, recapture)
+            };
+            self.extensions += extension;
+            let new_depth = depth - 1 + extension;
 
+            let quiet = !child.cp && child.mv.promotion.is_none();
             let value = if i == 0 {
-                -self.execute(&b1, depth - 1, -beta, -alpha, playing, child.cp)
+                -self.execute(&b1, new_depth, -beta, -alpha, playing, child.cp)
             } else {
-                let mut value =
-                    -self.execute(&b1, depth - 1, -alpha - 1, -alpha, playing, child.cp);
+                // Late move reductions for quiet moves, re-searched to full depth on a fail high
+                let reduce = quiet
+                    && extension == 0
+                    && !self.mate_search
+                    && !in_check
+                    && depth >= LMR_DEPTH_MIN
+                    && i >= LMR_MOVES_MIN
+                    && !killers.contains(&Some(child.mv))
+                    && b1.checkers().is_empty();
+                let mut value = alpha + 1;
+                if reduce {
+                    let reduced_depth = (new_depth - reduction(depth, i)).max(1);
+                    value = -self.execute(&b1, reduced_depth, -alpha - 1, -alpha, playing, false);
+                }
+                if value > alpha {
+                    value = -self.execute(&b1, new_depth, -alpha - 1, -alpha, playing, child.cp);
+                }
                 if value > alpha && value < beta {
-                    value = -self.execute(&b1, depth - 1, -beta, -value, playing, child.cp);
+                    value = -self.execute(&b1, new_depth, -beta, -value, playing, child.cp);
                 }
                 value
             };
+            i += 1;
 
+            self.extensions -= extension;
             self.history.dec(&b1);
 
             if value > best_value {
@@ -88,23 +249,339 @@ impl Pvs {
             }
 
             if best_value >= beta {
+                if quiet {
+                    self.move_order.update(
+                        board,
+                        ply,
+                        depth,
+                        child.mv,
+                        previous_move,
+                        &quiets_tried,
+                    );
+                }
                 break;
             }
 
+            if quiet {
+                quiets_tried.push(child.mv);
+            }
+
             if best_value > alpha {
                 alpha = best_value;
             }
         }
+        self.ply -= 1;
 
         if let Some(bm) = best_move {
-            self.store.put(depth - 1, best_value, board, &bm);
+            if playing.load(Ordering::Relaxed) && excluded_move.is_none() {
+                let bound = Bound::new(best_value, alpha_orig, beta);
+                let value = shift_mate(best_value, self.extensions);
+                self.store.put(depth, value, bound, board, &bm);
+            }
+        }
+        best_value
+    }
+
+    /// Singular extension: the stored move is extended if all other moves fail low
+    /// with a reduced search against a margin below its stored score.
+    fn singular(
+        &mut self,
+        board: &Board,
+        depth: Depth,
+        (hash_move, value, bound, stored_depth): (Move, MoveScore, Bound, Depth),
+        capture: bool,
+        playing: &Arc<AtomicBool>,
+    ) -> bool {
+        if depth < SINGULAR_DEPTH_MIN
+            || stored_depth < depth - 3
+            || bound == Bound::Upper
+            || value.abs() >= MATE_LEVEL
+            || self.extensions >= EXTENSIONS_MAX
+        {
+            return false;
+        }
+        let singular_beta = value - SINGULAR_MARGIN * MoveScore::from(depth);
+        self.excluded_move = Some(hash_move);
+        let value = self.execute(
+            board,
+            (depth - 1) / 2,
+            singular_beta - 1,
+            singular_beta,
+            playing,
+            capture,
+        );
+        value < singular_beta
+    }
+
+    /// Null move pruning: if passing the move still leads to a beta cutoff with a reduced search,
+    /// the position is good enough to be cut off without searching any move.
+    /// Not applied in check, after a null move, in pawn endgames prone to zugzwang, in mate search
+    /// and if the static evaluation is below beta anyway.
+    /// Near the root a cutoff is verified by a reduced search of the position itself.
+    fn null_move_cutoff(
+        &mut self,
+        board: &Board,
+        depth: Depth,
+        beta: MoveScore,
+        playing: &Arc<AtomicBool>,
+    ) -> bool {
+        if !self.null_move_pruning
+            || self.mate_search
+            || depth < NULL_MOVE_DEPTH_MIN
+            || self.previous_move.is_none()
+            || beta.abs() >= MATE_LEVEL
+        {
+            return false;
+        }
+        let pieces = board.colors(board.side_to_move())
+            & !board.pieces(Piece::Pawn)
+            & !board.pieces(Piece::King);
+        if pieces.is_empty() || Tapered::evaluate_with(board, &mut self.pawn_hash) < beta {
+            return false;
+        }
+        let Some(null_board) = board.null_move() else {
+            return false;
+        };
+
+        let reduction = 2 + depth / 4;
+        let previous_move = self.previous_move.take();
+        self.ply += 1;
+        let value = -self.execute(
+            &null_board,
+            depth - 1 - reduction,
+            -beta,
+            -beta + 1,
+            playing,
+            false,
+        );
+        self.ply -= 1;
+        self.previous_move = previous_move;
+        if value < beta {
+            return false;
+        }
+        if depth < NULL_MOVE_VERIFICATION_DEPTH {
+            return true;
+        }
+
+        // the verification search must not prune by a null move again
+        self.null_move_pruning = false;
+        let value = self.execute(board, depth - reduction, beta - 1, beta, playing, false);
+        self.null_move_pruning = true;
+        value >= beta
+    }
+
+    /// Search captures and promotions only until the position is quiet.
+    /// Captures losing material by static exchange evaluation are skipped.
+    /// Near the root all evasions of a check are searched, so mates are not missed.
+    fn quiescence(
+        &mut self,
+        board: &Board,
+        mut alpha: MoveScore,
+        beta: MoveScore,
+        q_ply: Depth,
+        playing: &Arc<AtomicBool>,
+    ) -> MoveScore {
+        if !self.count_node(playing) {
+            return 0;
+        }
+
+        self.sel_depth = self.sel_depth.max(self.ply);
+
+        // A mate search needs no quiet positions but as many plies as possible.
+        if self.mate_search {
+            return Tapered::evaluate_with(board, &mut self.pawn_hash);
+        }
+
+        let evasions = !board.checkers().is_empty() && q_ply < QUIESCENCE_CHECK_DEPTH;
+        let mut best_value: MoveScore = MIN_INT;
+
+        if !evasions {
+            best_value = Tapered::evaluate_with(board, &mut self.pawn_hash);
+            if best_value >= beta {
+                return best_value;
+            }
+            if best_value > alpha {
+                alpha = best_value;
+            }
         }
+
+        let children = board.get_legal_sorted(None);
+        if evasions && children.is_empty() {
+            return -MATE + MoveScore::from(q_ply + self.extensions);
+        }
+
+        self.ply += 1;
+        for child in children
+            .iter()
+            .filter(|c| evasions || (c.cp && c.sc >= 0) || c.mv.promotion.is_some())
+        {
+            let mut b1 = board.clone();
+            b1.play_unchecked(child.mv);
+
+            let value = -self.quiescence(&b1, -beta, -alpha, q_ply + 1, playing);
+
+            if value > best_value {
+                best_value = value;
+            }
+
+            if best_value >= beta {
+                break;
+            }
+
+            if best_value > alpha {
+                alpha = best_value;
+            }
+        }
+        self.ply -= 1;
+
         best_value
     }
+
+    /// Count a searched node. Returns false and stops the search once the node limit is exceeded.
+    fn count_node(&mut self, playing: &Arc<AtomicBool>) -> bool {
+        if !playing.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.node_count += 1;
+        if let Some(max_nodes) = self.max_nodes {
+            if self.shared_node_count.fetch_add(1, Ordering::Relaxed) >= max_nodes {
+                playing.store(false, Ordering::Relaxed);
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Get the extension of a move: checks, recaptures and pawn pushes to the seventh rank are extended by one ply.
+fn extension(board: &Board, b1: &Board, child: &AnnotatedMove, recapture: bool) -> Depth {
+    let seventh_rank = Rank::Seventh.relative_to(board.side_to_move());
+    if !b1.checkers().is_empty()
+        || (recapture && child.cp)
+        || (board.piece_on(child.mv.from) == Some(Piece::Pawn)
+            && child.mv.to.rank() == seventh_rank)
+    {
+        1
+    } else {
+        0
+    }
+}
+
+/// Shift a mate score by the given number of plies, away from zero.
+/// The transposition table converts mate scores by the remaining depth, to which extensions add.
+fn shift_mate(value: MoveScore, plies: Depth) -> MoveScore {
+    if value > MATE_LEVEL {
+        value + MoveScore::from(plies)
+    } else if value < -MATE_LEVEL {
+        value - MoveScore::from(plies)
+    } else {
+        value
+    }
+}
+
+/// Get the reduction of a late move given the remaining depth and its index in the move order.
+fn reduction(depth: Depth, index: usize) -> Depth {
+    static REDUCTIONS: OnceLock<[[Depth; 64]; 64]> = OnceLock::new();
+    let reductions = REDUCTIONS.get_or_init(|| {
+        let mut reductions = [[0; 64]; 64];
+        for (d, row) in reductions.iter_mut().enumerate().skip(1) {
+            for (i, r) in row.iter_mut().enumerate().skip(1) {
+                *r = (0.75 + (d as f64).ln() * (i as f64).ln() / 2.25) as Depth;
+            }
+        }
+        reductions
+    });
+    reductions[(depth as usize).min(63)][index.min(63)]
 }
 
 impl Default for Pvs {
     fn default() -> Self {
-        Self::new()
+        Self::new(Arc::new(Store::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_quiescence() {
+        let playing = Arc::new(AtomicBool::new(true));
+        let board = Board::from_str("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let mut pvs = Pvs::new(Arc::new(Store::new()));
+
+        let value_after = |pvs: &mut Pvs, mv: &str| {
+            let mut b1 = board.clone();
+            b1.play(mv.parse().unwrap());
+            -pvs.execute(&b1, 0, MIN_INT, MAX_INT, &playing, false)
+        };
+
+        // the pawn on d5 is defended, so taking it loses the queen
+        assert!(value_after(&mut pvs, "d2d5") < value_after(&mut pvs, "e1f1"));
+
+        // a mate at the horizon is still scored as mate
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1").unwrap();
+        let mut b1 = board.clone();
+        b1.play("g8h8".parse().unwrap());
+        b1.play("a1a8".parse().unwrap());
+        assert_eq!(
+            pvs.execute(&b1, 0, MIN_INT, MAX_INT, &playing, false),
+            -MATE
+        );
+    }
+    #[test]
+    fn test_null_move_pruning() {
+        let playing = Arc::new(AtomicBool::new(true));
+        let node_count = |fen: &str, null_move_pruning: bool| {
+            let board = Board::from_str(fen).unwrap();
+            let mut pvs = Pvs::new(Arc::new(Store::new()));
+            pvs.null_move_pruning = null_move_pruning;
+            pvs.previous_move = Some("e2e4".parse().unwrap());
+            pvs.execute(&board, 7, MIN_INT, MAX_INT, &playing, false);
+            pvs.node_count
+        };
+
+        let middlegame = "r1bqkb1r/4npp1/p1p4p/1p1pP1B1/8/1B6/PPPN1PPP/R2Q1RK1 w kq - 0 1";
+        assert!(node_count(middlegame, true) < node_count(middlegame, false));
+
+        // no null moves in a pawn endgame, where zugzwang is common
+        let pawn_endgame = "8/5k2/4p3/3pP3/3P4/4K3/8/8 w - - 0 1";
+        assert_eq!(
+            node_count(pawn_endgame, true),
+            node_count(pawn_endgame, false)
+        );
+    }
+    #[test]
+    fn test_extension() {
+        let extension_of = |fen: &str, mv: &str, recapture: bool| {
+            let board = Board::from_str(fen).unwrap();
+            let child = board
+                .get_legal_sorted(None)
+                .into_iter()
+                .find(|m| m.mv.to_string() == mv)
+                .unwrap();
+            let mut b1 = board.clone();
+            b1.play_unchecked(child.mv);
+            extension(&board, &b1, &child, recapture)
+        };
+
+        let fen = "6k1/1P1r4/8/3p4/8/8/5PPP/3R2K1 w - - 0 1";
+        assert_eq!(extension_of(fen, "d1d5", false), 0);
+        assert_eq!(extension_of(fen, "d1d5", true), 1);
+        assert_eq!(extension_of(fen, "h2h3", false), 0);
+        // a promotion is no push to the seventh rank, but this one checks
+        assert_eq!(extension_of(fen, "b7b8q", false), 1);
+        assert_eq!(extension_of(fen, "b7b8n", false), 0);
+        let fen = "3r2k1/8/1P6/3p4/8/8/5PPP/3R2K1 w - - 0 1";
+        assert_eq!(extension_of(fen, "b6b7", false), 1);
+    }
+    #[test]
+    fn test_reduction() {
+        assert_eq!(reduction(1, 1), 0);
+        assert_eq!(reduction(3, 3), 1);
+        assert!(reduction(10, 20) > reduction(10, 3));
+        assert!(reduction(20, 10) > reduction(3, 10));
+        assert_eq!(reduction(200, 200), reduction(63, 63));
     }
 }
@@ -0,0 +1,73 @@
+use super::{constants::*, store::Bound};
+use crate::misc::types::*;
+use cozy_chess::{Board, Move};
+
+/// A score as presented to the user: either centipawns or moves until mate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    Cp(MoveScore),
+    Mate(MoveScore),
+}
+
+impl Score {
+    /// Convert a search value of a root move searched to the given depth.
+    /// Values beyond `MATE_LEVEL` are converted to the number of moves until mate,
+    /// negative if the side to move is getting mated.
+    pub fn new(value: MoveScore, depth: Depth) -> Self {
+        if value.abs() <= MATE_LEVEL {
+            return Score::Cp(value * 100 / PAWN_VALUE);
+        }
+        let plies = (MoveScore::from(depth) + 1 + MATE - value.abs()).max(1);
+        if value > 0 {
+            Score::Mate((plies + 1) / 2)
+        } else {
+            Score::Mate(-(plies + 1) / 2)
+        }
+    }
+}
+
+/// A root move ranked by its score, together with its principal variation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub score: Score,
+    pub pv: Vec<Move>,
+}
+
+/// Progress of the search, reported after every completed iteration for every line
+/// and for the best line whenever the score falls outside the aspiration window.
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: Depth,
+    pub sel_depth: Depth,
+    pub multi_pv: usize,
+    pub score: Score,
+    pub bound: Bound, // the score is a lower or upper bound if the aspiration window failed
+    pub nodes: u64,
+    pub nps: u64,
+    pub time: MoveTime, // in Milliseconds
+    pub hashfull: u32,  // in permille
+    pub curr_move: Option<Move>,
+    pub pv: Vec<Move>,
+}
+
+/// A receiver of search progress, given the root position and the search info.
+pub type Reporter = Box<dyn Fn(&Board, &SearchInfo) + Send + Sync>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score() {
+        assert_eq!(Score::new(0, 5), Score::Cp(0));
+        assert_eq!(Score::new(300, 5), Score::Cp(150));
+        assert_eq!(Score::new(-200, 5), Score::Cp(-100));
+
+        // mate found at the first ply after the root move
+        assert_eq!(Score::new(MATE + 3, 3), Score::Mate(1));
+        // mate found at the third ply after the root move
+        assert_eq!(Score::new(MATE + 1, 3), Score::Mate(2));
+        // getting mated at the second ply after the root move
+        assert_eq!(Score::new(-MATE - 2, 3), Score::Mate(-1));
+    }
+}
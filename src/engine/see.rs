@@ -0,0 +1,142 @@
+use super::constants::PIECE_VALUES;
+use crate::misc::types::*;
+use cozy_chess::{
+    get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves, BitBoard,
+    Board, Color, Move, Piece, Square,
+};
+
+/// A trait to extend the board of crate Cozy Chess by a static exchange evaluation.
+pub trait StaticExchange {
+    fn attackers(&self, square: Square, occupied: BitBoard) -> BitBoard;
+    fn see(&self, mv: Move) -> MoveScore;
+}
+
+impl StaticExchange for Board {
+    /// Get the pieces of both colors attacking the given square, given the occupied squares.
+    fn attackers(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        let rooks = self.pieces(Piece::Rook) | self.pieces(Piece::Queen);
+        let bishops = self.pieces(Piece::Bishop) | self.pieces(Piece::Queen);
+        let attackers = (get_rook_moves(square, occupied) & rooks)
+            | (get_bishop_moves(square, occupied) & bishops)
+            | (get_knight_moves(square) & self.pieces(Piece::Knight))
+            | (get_king_moves(square) & self.pieces(Piece::King))
+            | (get_pawn_attacks(square, Color::White)
+                & self.colored_pieces(Color::Black, Piece::Pawn))
+            | (get_pawn_attacks(square, Color::Black)
+                & self.colored_pieces(Color::White, Piece::Pawn));
+        attackers & occupied
+    }
+
+    /// Get the material balance of the exchange started by the given move on its target square,
+    /// both sides recapturing with their least valuable piece and stopping when it suits them.
+    /// Negative if the move loses material, pins are not considered.
+    fn see(&self, mv: Move) -> MoveScore {
+        let Some(moving) = self.piece_on(mv.from) else {
+            return 0;
+        };
+        if self.color_on(mv.to) == Some(self.side_to_move()) {
+            // castling
+            return 0;
+        }
+
+        let mut occupied = self.occupied() ^ mv.from.bitboard();
+        let captured = match self.piece_on(mv.to) {
+            Some(piece) => PIECE_VALUES[piece as usize],
+            None if moving == Piece::Pawn && mv.from.file() != mv.to.file() => {
+                occupied ^= Square::new(mv.to.file(), mv.from.rank()).bitboard();
+                PIECE_VALUES[Piece::Pawn as usize]
+            }
+            None => 0,
+        };
+
+        let mut gain = [0; 32];
+        let mut on_square = moving;
+        gain[0] = captured;
+        if let Some(promotion) = mv.promotion {
+            gain[0] += PIECE_VALUES[promotion as usize] - PIECE_VALUES[Piece::Pawn as usize];
+            on_square = promotion;
+        }
+
+        let mut color = !self.side_to_move();
+        let mut d = 0;
+        loop {
+            let attackers = self.attackers(mv.to, occupied);
+            let own = attackers & self.colors(color);
+            let Some((piece, square)) = Piece::ALL
+                .iter()
+                .find_map(|&p| (own & self.pieces(p)).next_square().map(|sq| (p, sq)))
+            else {
+                break;
+            };
+            // the king must not capture a defended piece
+            if piece == Piece::King && !(attackers & self.colors(!color)).is_empty() {
+                break;
+            }
+
+            d += 1;
+            gain[d] = PIECE_VALUES[on_square as usize] - gain[d - 1];
+            // stop if the capture cannot change the outcome anymore
+            if (-gain[d - 1]).max(gain[d]) < 0 || d == gain.len() - 1 {
+                d -= 1;
+                break;
+            }
+
+            occupied ^= square.bitboard();
+            on_square = piece;
+            color = !color;
+        }
+
+        while d > 0 {
+            gain[d - 1] = -(-gain[d - 1]).max(gain[d]);
+            d -= 1;
+        }
+        gain[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_see() {
+        let see = |fen: &str, mv: &str| Board::from_str(fen).unwrap().see(mv.parse().unwrap());
+        let pawn = PIECE_VALUES[Piece::Pawn as usize];
+        let knight = PIECE_VALUES[Piece::Knight as usize];
+        let bishop = PIECE_VALUES[Piece::Bishop as usize];
+        let rook = PIECE_VALUES[Piece::Rook as usize];
+
+        // undefended pawn
+        assert_eq!(
+            see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"),
+            pawn
+        );
+        // pawn defended by a knight, the rook is lost
+        assert_eq!(
+            see(
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5"
+            ),
+            pawn - knight
+        );
+        // bishop takes a knight defended by a pawn
+        assert_eq!(
+            see("4k3/8/2p5/3n4/8/5B2/8/4K3 w - - 0 1", "f3d5"),
+            knight - bishop
+        );
+        // x-ray: the queen behind the rook recaptures
+        assert_eq!(see("4k3/3r4/8/8/3p4/8/3R4/3QK3 w - - 0 1", "d2d4"), pawn);
+        // the king cannot recapture a defended rook
+        assert_eq!(see("8/8/3k4/4p3/8/2B5/4R3/4K3 w - - 0 1", "e2e5"), pawn);
+        assert_eq!(
+            see("8/8/3k4/4p3/8/8/4R3/4K3 w - - 0 1", "e2e5"),
+            pawn - rook
+        );
+        // en passant and a quiet move
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), pawn);
+        assert_eq!(see("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), 0);
+        // a quiet move to an attacked square loses the piece
+        assert_eq!(see("4k3/8/5p2/8/5B2/8/8/4K3 w - - 0 1", "f4g5"), -bishop);
+    }
+}
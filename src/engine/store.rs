@@ -1,63 +1,309 @@
+use super::constants::{DEFAULT_HASH_SIZE, MATE_LEVEL};
 use crate::misc::types::*;
-use cozy_chess::{Board, Move};
-use hashbrown::hash_map::Entry::Occupied;
-use hashbrown::hash_map::Entry::Vacant;
-use hashbrown::HashMap;
-
-/// A transposition table.
-#[derive(Clone)]
-pub struct Item {
+use cozy_chess::{Board, Move, Piece, Square};
+use std::collections::TryReserveError;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// The kind of a stored score: exact, or only a bound because of an Alpha or Beta cutoff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+impl Bound {
+    /// Get the kind of the score found by a search with the given Alpha and Beta.
+    pub fn new(value: MoveScore, alpha: MoveScore, beta: MoveScore) -> Self {
+        if value >= beta {
+            Bound::Lower
+        } else if value > alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        }
+    }
+
+    /// Check whether a stored score can be used instead of a search with the given Alpha and Beta.
+    pub fn permits_cutoff(self, value: MoveScore, alpha: MoveScore, beta: MoveScore) -> bool {
+        match self {
+            Bound::Exact => true,
+            Bound::Lower => value >= beta,
+            Bound::Upper => value <= alpha,
+        }
+    }
+}
+
+/// An entry of the transposition table, packed into 64 bits:
+/// the move, the score, the depth, the bound and the age of the search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Item {
     depth: Depth,
     value: MoveScore,
+    bound: Bound,
     chessmove: Move,
+    age: u8,
+}
+
+impl Item {
+    /// Pack the item, an empty slot is zero.
+    fn pack(&self) -> u64 {
+        let promotion = self.chessmove.promotion.map_or(0, |p| p as u64 + 1);
+        let chessmove =
+            self.chessmove.from as u64 | (self.chessmove.to as u64) << 6 | promotion << 12;
+        let bound = match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        chessmove
+            | (self.value as u32 as u64) << 16
+            | (self.depth.clamp(0, u8::MAX as Depth) as u64) << 48
+            | bound << 56
+            | (self.age as u64 & AGE_MASK) << 58
+    }
+
+    /// Unpack an item, None for an empty slot.
+    fn unpack(data: u64) -> Option<Self> {
+        let bound = match (data >> 56) & 3 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None,
+        };
+        let promotion = match (data >> 12) & 7 {
+            0 => None,
+            p => Piece::try_index(p as usize - 1),
+        };
+        Some(Self {
+            depth: ((data >> 48) & 0xff) as Depth,
+            value: (data >> 16) as u32 as MoveScore,
+            bound,
+            chessmove: Move {
+                from: Square::index((data & 63) as usize),
+                to: Square::index(((data >> 6) & 63) as usize),
+                promotion,
+            },
+            age: (data >> 58) as u8,
+        })
+    }
 }
 
-/// A hashmap for use in the transposition table.
+/// Ages are counted modulo 64.
+const AGE_MASK: u64 = 63;
+
+/// Number of entries sharing a bucket.
+const BUCKET_SIZE: usize = 4;
+
+/// An entry is stored as key xor data beside the data, so a torn write by another thread
+/// is detected as a different key.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn load(&self) -> (u64, u64) {
+        let key = self.key.load(Ordering::Relaxed);
+        let data = self.data.load(Ordering::Relaxed);
+        (key ^ data, data)
+    }
+
+    fn store(&self, key: u64, data: u64) {
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+/// The entries of a bucket share a cache line.
+#[derive(Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; BUCKET_SIZE],
+}
+
+/// A transposition table of fixed size, shared by all search threads without locking.
+/// Within a bucket the entries of older searches and of lower depth are replaced first.
 pub struct Store {
-    pub h: HashMap<u64, Item>,
+    buckets: Vec<Bucket>,
+    age: AtomicU8,
 }
 
 impl Store {
     /// Constructor
     pub fn new() -> Self {
-        Self { h: HashMap::new() }
+        let mut store = Self {
+            buckets: Vec::new(),
+            age: AtomicU8::new(0),
+        };
+        store
+            .set_size(DEFAULT_HASH_SIZE)
+            .expect("Transposition table cannot be allocated");
+        store
+    }
+
+    /// Resize the table to the given size in megabytes, rounded down to a power of two of buckets.
+    /// All entries are removed. If the memory cannot be allocated, the table is left empty.
+    pub fn set_size(&mut self, megabytes: usize) -> Result<(), TryReserveError> {
+        let count = megabytes * 1024 * 1024 / size_of::<Bucket>();
+        let count = match count {
+            0 => 0,
+            c => 1 << c.ilog2(),
+        };
+        self.buckets = Vec::new();
+        self.buckets.try_reserve_exact(count)?;
+        self.buckets.resize_with(count, Bucket::default);
+        Ok(())
     }
 
-    /// Put a position, its score and depth and the best move into the transposition table.
-    /// Update the score only if depth is greater than already stored depth.
-    pub fn put(&mut self, depth: Depth, value: MoveScore, b: &Board, chessmove: &Move) {
+    /// Remove all entries.
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flat_map(|b| b.slots.iter()) {
+            slot.store(0, 0);
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Start a new search, so that the entries of former searches are replaced first.
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Put a position, its score, bound and depth and the best move into the transposition table.
+    /// An entry of the same position is kept if it was searched deeper during the same search.
+    pub fn put(&self, depth: Depth, value: MoveScore, bound: Bound, b: &Board, chessmove: &Move) {
+        let Some(bucket) = self.bucket(b) else {
+            return;
+        };
         let key = b.hash_without_ep();
+        let age = self.age();
+        let same_position = bucket.slots.iter().find_map(|slot| {
+            let (slot_key, data) = slot.load();
+            Item::unpack(data)
+                .filter(|_| slot_key == key)
+                .map(|item| (slot, item))
+        });
+        let victim = match same_position {
+            Some((_, item)) if item.age == age && item.depth > depth && bound != Bound::Exact => {
+                return;
+            }
+            Some((slot, _)) => slot,
+            None => bucket
+                .slots
+                .iter()
+                .min_by_key(|slot| match Item::unpack(slot.load().1) {
+                    Some(item) => {
+                        let relative_age = age.wrapping_sub(item.age) & AGE_MASK as u8;
+                        MoveScore::from(item.depth) - 8 * MoveScore::from(relative_age)
+                    }
+                    None => MoveScore::MIN,
+                })
+                .unwrap_or(&bucket.slots[0]),
+        };
+
         let item = Item {
             depth,
-            value,
+            value: value_to_store(value, depth),
+            bound,
             chessmove: *chessmove,
+            age,
         };
-        match self.h.entry(key) {
-            Occupied(mut entry) => {
-                if entry.get().depth <= depth {
-                    entry.insert(item);
+        victim.store(key, item.pack());
+    }
+
+    /// Get a move, its score, the bound of the score and the depth searched for the given position.
+    /// Mate scores are converted to the given remaining depth.
+    pub fn get(&self, depth: Depth, b: &Board) -> Option<(Move, MoveScore, Bound, Depth)> {
+        let key = b.hash_without_ep();
+        self.bucket(b)?.slots.iter().find_map(|slot| {
+            let (slot_key, data) = slot.load();
+            let item = Item::unpack(data).filter(|_| slot_key == key)?;
+            Some((
+                item.chessmove,
+                value_from_store(item.value, depth),
+                item.bound,
+                item.depth,
+            ))
+        })
+    }
+
+    /// Get the principal variation for the given position by following the stored best moves.
+    pub fn pv(&self, b: &Board, max_len: usize) -> Vec<Move> {
+        let mut result = Vec::new();
+        let mut board = b.clone();
+        let mut seen = vec![board.hash()];
+        while result.len() < max_len {
+            match self.get(0, &board) {
+                Some((mv, ..)) if board.is_legal(mv) => {
+                    board.play_unchecked(mv);
+                    result.push(mv);
+                    if seen.contains(&board.hash()) {
+                        break;
+                    }
+                    seen.push(board.hash());
                 }
-            }
-            Vacant(entry) => {
-                entry.insert(item);
+                _ => break,
             }
         }
+        result
     }
 
-    /// Get a move and its score for the given position.
-    pub fn get(&self, depth: Depth, b: &Board) -> Option<(Move, MoveScore, bool)> {
-        let key = b.hash_without_ep();
-        match self.h.get(&key) {
-            Some(item) => {
-                if item.depth < depth {
-                    Some((item.chessmove, item.value, false))
-                } else {
-                    Some((item.chessmove, item.value, true))
-                }
+    /// Give the fill level of the table in permille, sampling entries of the current search.
+    pub fn hashfull(&self) -> u32 {
+        let age = self.age();
+        let samples = self
+            .buckets
+            .iter()
+            .take(1000 / BUCKET_SIZE)
+            .flat_map(|b| b.slots.iter());
+        let (count, used) = samples.fold((0, 0), |(count, used), slot| {
+            match Item::unpack(slot.load().1) {
+                Some(item) if item.age == age => (count + 1, used + 1),
+                _ => (count + 1, used),
             }
-            None => None,
+        });
+        match count {
+            0 => 1000,
+            c => used * 1000 / c,
+        }
+    }
+
+    /// Get the bucket of a position, None if the table has no entries.
+    fn bucket(&self, b: &Board) -> Option<&Bucket> {
+        match self.buckets.len() {
+            0 => None,
+            len => Some(&self.buckets[b.hash_without_ep() as usize & (len - 1)]),
         }
     }
+
+    /// Get the age of the current search.
+    fn age(&self) -> u8 {
+        self.age.load(Ordering::Relaxed) & AGE_MASK as u8
+    }
+}
+
+/// Mate scores depend on the remaining depth, store them as distance to mate from the position.
+fn value_to_store(value: MoveScore, depth: Depth) -> MoveScore {
+    if value > MATE_LEVEL {
+        value - MoveScore::from(depth)
+    } else if value < -MATE_LEVEL {
+        value + MoveScore::from(depth)
+    } else {
+        value
+    }
+}
+
+/// Convert a stored mate score back given the remaining depth.
+fn value_from_store(value: MoveScore, depth: Depth) -> MoveScore {
+    if value > MATE_LEVEL {
+        value + MoveScore::from(depth)
+    } else if value < -MATE_LEVEL {
+        value - MoveScore::from(depth)
+    } else {
+        value
+    }
 }
 
 impl Default for Store {
@@ -71,37 +317,199 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
-    use crate::engine::game::Game;
+    use crate::engine::{constants::MATE, game::Game};
 
     #[test]
     fn test_store() {
         let g = Game::new("".to_string(), 10, 10000);
-        let mut store = Store::new();
+        let store = Store::new();
 
         let result = store.get(5, &g.board);
         assert_eq!(result, None);
 
-        store.put(5, 300, &g.board, &Move::from_str("c2c4").unwrap());
+        store.put(
+            5,
+            300,
+            Bound::Exact,
+            &g.board,
+            &Move::from_str("c2c4").unwrap(),
+        );
 
-        let (m, v, fresh) = store.get(5, &g.board).unwrap();
+        let (m, v, bound, depth) = store.get(5, &g.board).unwrap();
         assert_eq!(v, 300);
+        assert_eq!(bound, Bound::Exact);
         assert_eq!(m.to_string(), "c2c4");
-        assert_eq!(fresh, true);
-
-        let (m, _, fresh) = store.get(6, &g.board).unwrap();
-        assert_eq!(m.to_string(), "c2c4");
-        assert_eq!(fresh, false);
+        assert_eq!(depth, 5);
 
-        let (m, v, fresh) = store.get(4, &g.board).unwrap();
+        let (m, v, _, _) = store.get(4, &g.board).unwrap();
         assert_eq!(v, 300);
         assert_eq!(m.to_string(), "c2c4");
-        assert_eq!(fresh, true);
 
-        store.put(5, 305, &g.board, &Move::from_str("e2e4").unwrap());
+        store.put(
+            5,
+            305,
+            Bound::Lower,
+            &g.board,
+            &Move::from_str("e2e4").unwrap(),
+        );
 
-        let (m, v, fresh) = store.get(4, &g.board).unwrap();
+        let (m, v, bound, depth) = store.get(4, &g.board).unwrap();
         assert_eq!(v, 305);
+        assert_eq!(bound, Bound::Lower);
         assert_eq!(m.to_string(), "e2e4");
-        assert_eq!(fresh, true);
+        assert_eq!(depth, 5);
+    }
+
+    #[test]
+    fn test_bound() {
+        assert_eq!(Bound::new(100, 0, 100), Bound::Lower);
+        assert_eq!(Bound::new(50, 0, 100), Bound::Exact);
+        assert_eq!(Bound::new(0, 0, 100), Bound::Upper);
+
+        assert!(Bound::Exact.permits_cutoff(50, 0, 100));
+        assert!(Bound::Lower.permits_cutoff(150, 0, 100));
+        assert!(!Bound::Lower.permits_cutoff(50, 0, 100));
+        assert!(Bound::Upper.permits_cutoff(-50, 0, 100));
+        assert!(!Bound::Upper.permits_cutoff(50, 0, 100));
+    }
+
+    #[test]
+    fn test_mate_value() {
+        let g = Game::new("".to_string(), 10, 10000);
+        let store = Store::new();
+
+        // mate found at remaining depth 2 by a search to depth 5, i.e. after three plies
+        store.put(
+            5,
+            MATE + 2,
+            Bound::Exact,
+            &g.board,
+            &Move::from_str("e2e4").unwrap(),
+        );
+        // still three plies, if the position is reached with remaining depth 7
+        let (_, v, _, _) = store.get(7, &g.board).unwrap();
+        assert_eq!(v, MATE + 4);
+
+        store.put(
+            8,
+            -MATE - 2,
+            Bound::Exact,
+            &g.board,
+            &Move::from_str("e2e4").unwrap(),
+        );
+        let (_, v, _, _) = store.get(6, &g.board).unwrap();
+        assert_eq!(v, -MATE);
+    }
+
+    #[test]
+    fn test_pv() {
+        let g = Game::new("".to_string(), 10, 10000);
+        let store = Store::new();
+        assert!(store.pv(&g.board, 5).is_empty());
+
+        let mut b = g.board.clone();
+        for mv in ["e2e4", "e7e5", "g1f3"] {
+            let mv = Move::from_str(mv).unwrap();
+            store.put(3, 0, Bound::Exact, &b, &mv);
+            b.play_unchecked(mv);
+        }
+        let pv: Vec<String> = store
+            .pv(&g.board, 5)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(pv, vec!["e2e4", "e7e5", "g1f3"]);
+        assert_eq!(store.pv(&g.board, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_size() {
+        let g = Game::new("".to_string(), 10, 10000);
+        let mut store = Store::new();
+        assert_eq!(store.buckets.len(), 1 << 18);
+
+        store.set_size(0).unwrap();
+        store.put(
+            5,
+            300,
+            Bound::Exact,
+            &g.board,
+            &Move::from_str("c2c4").unwrap(),
+        );
+        assert_eq!(store.get(5, &g.board), None);
+        assert_eq!(store.hashfull(), 1000);
+
+        store.set_size(3).unwrap();
+        assert_eq!(store.buckets.len(), 1 << 15);
+        store.put(
+            5,
+            300,
+            Bound::Exact,
+            &g.board,
+            &Move::from_str("c2c4").unwrap(),
+        );
+        assert!(store.get(5, &g.board).is_some());
+        assert!(store.hashfull() <= 1);
+
+        store.clear();
+        assert_eq!(store.get(5, &g.board), None);
+
+        // a size beyond the memory is refused
+        assert!(store.set_size(usize::MAX >> 20).is_err());
+        assert!(store.buckets.is_empty());
+    }
+
+    #[test]
+    fn test_item() {
+        for (mv, value, bound) in [
+            ("e2e4", 0, Bound::Exact),
+            ("a7a8q", -MATE - 3, Bound::Lower),
+            ("h2h1n", MATE + 1, Bound::Upper),
+        ] {
+            let item = Item {
+                depth: 17,
+                value,
+                bound,
+                chessmove: Move::from_str(mv).unwrap(),
+                age: 63,
+            };
+            assert_eq!(Item::unpack(item.pack()), Some(item));
+        }
+        assert_eq!(Item::unpack(0), None);
+    }
+
+    #[test]
+    fn test_replacement() {
+        let mut store = Store::new();
+        store.buckets.truncate(1);
+        let mv = Move::from_str("e2e4").unwrap();
+        let boards: Vec<Board> = ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6"]
+            .iter()
+            .scan(Board::default(), |b, m| {
+                b.play(Move::from_str(m).unwrap());
+                Some(b.clone())
+            })
+            .collect();
+
+        for (b, depth) in boards.iter().zip([5, 2, 7, 4]) {
+            store.put(depth, 0, Bound::Exact, b, &mv);
+        }
+        // the shallowest entry is replaced
+        store.put(1, 0, Bound::Exact, &boards[4], &mv);
+        assert!(store.get(0, &boards[1]).is_none());
+        assert!(store.get(0, &boards[4]).is_some());
+
+        // a deeper entry of the same position and search is kept
+        store.put(3, 100, Bound::Lower, &boards[0], &mv);
+        assert_eq!(store.get(0, &boards[0]).unwrap().1, 0);
+
+        // entries of former searches are replaced first
+        store.new_search();
+        store.put(1, 0, Bound::Exact, &boards[5], &mv);
+        assert!(store.get(0, &boards[4]).is_none());
+        assert!(store.get(0, &boards[5]).is_some());
+        for b in [&boards[0], &boards[2], &boards[3]] {
+            assert!(store.get(0, b).is_some());
+        }
     }
 }
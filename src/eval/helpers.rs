@@ -1,6 +1,6 @@
-use super::constants::CB_RANK_1;
+use super::constants::{CB_RANK_1, PHASE_WEIGHTS};
 use crate::misc::types::*;
-use cozy_chess::{Board, Color, Piece};
+use cozy_chess::{Board, Color, Piece, Square};
 use std::cmp::max;
 
 /// Gives the number of available moves for the defending king.
@@ -20,6 +20,22 @@ pub fn defending_kings_moves_count(b: &Board) -> usize {
     }
 }
 
+/// Give the game phase from the pieces on the board, `PHASE_MAX` for the start position and 0 for a pawn endgame.
+pub fn game_phase(b: &Board) -> MoveScore {
+    Piece::ALL
+        .iter()
+        .map(|&p| b.pieces(p).len() as MoveScore * PHASE_WEIGHTS[p as usize])
+        .sum()
+}
+
+/// Calculate the distance between two squares in king moves.
+pub fn square_distance(a: Square, b: Square) -> MoveScore {
+    max(
+        MoveScore::abs(a.rank() as MoveScore - b.rank() as MoveScore),
+        MoveScore::abs(a.file() as MoveScore - b.file() as MoveScore),
+    )
+}
+
 /// Calculate the distance between both kings.
 pub fn kings_distance(b: &Board) -> MoveScore {
     let wk = b
@@ -30,10 +46,7 @@ pub fn kings_distance(b: &Board) -> MoveScore {
         .colored_pieces(Color::Black, Piece::King)
         .next_square()
         .unwrap();
-    max(
-        MoveScore::abs(wk.rank() as MoveScore - bk.rank() as MoveScore),
-        MoveScore::abs(wk.file() as MoveScore - bk.file() as MoveScore),
-    )
+    square_distance(wk, bk)
 }
 
 /// Fill the bitboard to the north of the set field.
@@ -78,6 +91,7 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::eval::constants::PHASE_MAX;
     use cozy_chess::FenParseError;
 
     #[test]
@@ -94,6 +108,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_game_phase() -> Result<(), FenParseError> {
+        assert_eq!(game_phase(&Board::default()), PHASE_MAX);
+        let board = Board::from_str("4k3/pppp4/8/8/8/8/3P4/R3K3 w - - 0 1")?;
+        assert_eq!(game_phase(&board), 2);
+        let board = Board::from_str("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1")?;
+        assert_eq!(game_phase(&board), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_square_distance() {
+        assert_eq!(square_distance(Square::A1, Square::A1), 0);
+        assert_eq!(square_distance(Square::A1, Square::H8), 7);
+        assert_eq!(square_distance(Square::E4, Square::C5), 2);
+    }
+
     #[test]
     fn test_kings_distance() -> Result<(), FenParseError> {
         let board = Board::from_str("8/7k/8/5r2/1KN5/2R5/8/8 w - - 0 1")?;
@@ -0,0 +1,121 @@
+use super::{constants::*, helpers::*, mobility::piece_attacks, tapered::PhaseScore};
+use crate::misc::types::*;
+use cozy_chess::{get_king_moves, Board, Color, Piece, Square};
+
+/// Give the safety of the king of the given color, in centipawns: its pawn shield, the enemy pawns
+/// storming it, open files next to it and the enemy pieces attacking the squares around it.
+/// King safety matters in the midgame only.
+pub fn king_safety(b: &Board, color: Color) -> PhaseScore {
+    let king = b.king(color);
+    let files = king.file().bitboard() | king.file().adjacent();
+    let own_pawns = b.colored_pieces(color, Piece::Pawn);
+    let enemy_pawns = b.colored_pieces(!color, Piece::Pawn);
+    let mut value: MoveScore = 0;
+
+    // Pawns in front of the king, by their distance in ranks
+    let distance = |square: Square| {
+        square.rank().relative_to(color) as MoveScore - king.rank().relative_to(color) as MoveScore
+    };
+    for square in own_pawns & files {
+        let d = distance(square);
+        if (1..=KING_SHIELD.len() as MoveScore).contains(&d) {
+            value += KING_SHIELD[d as usize - 1];
+        }
+    }
+    for square in enemy_pawns & files {
+        let d = distance(square);
+        if (1..=KING_PAWN_STORM.len() as MoveScore).contains(&d) {
+            value -= KING_PAWN_STORM[d as usize - 1];
+        }
+    }
+
+    // Files next to the king without own pawns
+    let open = open_files(b);
+    let half_open = half_open_files(b) & !file_fill(own_pawns.0);
+    value -= (files.0 & open & CB_RANK_1).count_ones() as MoveScore * KING_OPEN_FILE;
+    value -= (files.0 & half_open & CB_RANK_1).count_ones() as MoveScore * KING_HALF_OPEN_FILE;
+
+    value -= king_attack(b, color);
+    PhaseScore::new(value, 0)
+}
+
+/// Attack units of the enemy pieces attacking the king zone of the given color, weighted by piece
+/// and attacked squares. A single attacker is not considered dangerous.
+fn king_attack(b: &Board, color: Color) -> MoveScore {
+    let king = b.king(color);
+    let zone = get_king_moves(king) | king.bitboard();
+    let occupied = b.occupied();
+    let mut attackers = 0;
+    let mut units: MoveScore = 0;
+
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for square in b.colored_pieces(!color, piece) {
+            let attacked = piece_attacks(piece, square, occupied) & zone;
+            if !attacked.is_empty() {
+                attackers += 1;
+                units += KING_ATTACK_WEIGHTS[piece as usize] * attacked.len() as MoveScore;
+            }
+        }
+    }
+
+    if attackers < 2 {
+        return 0;
+    }
+    let units = units.min(KING_ATTACK_UNITS_MAX);
+    units * units / 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn safety(fen: &str, color: Color) -> MoveScore {
+        king_safety(&Board::from_str(fen).unwrap(), color).mg
+    }
+
+    #[test]
+    fn test_pawn_shield() {
+        let intact = "6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1";
+        let advanced = "6k1/5ppp/8/8/8/6PP/5P2/6K1 w - - 0 1";
+        let missing = "6k1/5ppp/8/8/8/8/5PP1/6K1 w - - 0 1";
+        assert_eq!(safety(intact, Color::White), safety(intact, Color::Black));
+        assert!(safety(intact, Color::White) > safety(advanced, Color::White));
+        assert!(safety(advanced, Color::White) > safety(missing, Color::White));
+    }
+
+    #[test]
+    fn test_pawn_storm() {
+        let calm = "6k1/8/8/6pp/8/8/5PPP/6K1 w - - 0 1";
+        let storm = "6k1/8/8/8/6pp/8/5PPP/6K1 w - - 0 1";
+        assert!(safety(calm, Color::White) > safety(storm, Color::White));
+    }
+
+    #[test]
+    fn test_king_files() {
+        let closed = "6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1";
+        let half_open = "6k1/5ppp/8/8/8/8/5P1P/6K1 w - - 0 1";
+        let open = "6k1/5p1p/8/8/8/8/5P1P/6K1 w - - 0 1";
+        assert!(safety(closed, Color::White) > safety(half_open, Color::White));
+        assert!(safety(half_open, Color::White) > safety(open, Color::White));
+        // a file half open for White only
+        assert_eq!(
+            safety(half_open, Color::Black),
+            safety(closed, Color::Black)
+        );
+    }
+
+    #[test]
+    fn test_king_attack() {
+        let b = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(king_attack(&b, Color::White), 0);
+
+        // a single attacker is no danger
+        let b = Board::from_str("6k1/5ppp/8/8/8/7q/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(king_attack(&b, Color::White), 0);
+
+        let b = Board::from_str("6k1/5ppp/8/8/6n1/7q/5PPP/6K1 w - - 0 1").unwrap();
+        assert!(king_attack(&b, Color::White) > 0);
+        assert_eq!(king_attack(&b, Color::Black), 0);
+    }
+}
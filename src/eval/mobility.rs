@@ -0,0 +1,180 @@
+use super::{constants::*, pawns::*, tapered::PhaseScore};
+use crate::misc::types::*;
+use cozy_chess::{
+    get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves, BitBoard,
+    Board, Color, Piece, Rank, Square,
+};
+
+/// Give the squares attacked by a knight, bishop, rook or queen on the given square.
+pub fn piece_attacks(piece: Piece, square: Square, occupied: BitBoard) -> BitBoard {
+    match piece {
+        Piece::Knight => get_knight_moves(square),
+        Piece::Bishop => get_bishop_moves(square, occupied),
+        Piece::Rook => get_rook_moves(square, occupied),
+        _ => get_bishop_moves(square, occupied) | get_rook_moves(square, occupied),
+    }
+}
+
+/// Give all squares attacked by the pieces of the given color.
+pub fn attacks(b: &Board, color: Color) -> BitBoard {
+    let occupied = b.occupied();
+    let mut attacked = pawn_attacks(b, color) | get_king_moves(b.king(color));
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for square in b.colored_pieces(color, piece) {
+            attacked |= piece_attacks(piece, square, occupied);
+        }
+    }
+    attacked
+}
+
+/// Give the activity of the pieces of the given color, in centipawns: their mobility, outposts,
+/// the bishop pair, rooks behind passed pawns, and trapped and hanging pieces.
+/// The passed pawns of both colors are given, as they are cached in the pawn hash table.
+pub fn piece_activity(b: &Board, color: Color, passed: BitBoard) -> PhaseScore {
+    let occupied = b.occupied();
+    let own_pawns = b.colored_pieces(color, Piece::Pawn);
+    let enemy_pawns = b.colored_pieces(!color, Piece::Pawn);
+    let enemy_pawn_attacks = pawn_attacks(b, !color);
+    let safe = !b.colors(color) & !enemy_pawn_attacks;
+    let mut score = PhaseScore::default();
+
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for square in b.colored_pieces(color, piece) {
+            let attacked = piece_attacks(piece, square, occupied);
+            let moves = (attacked & safe).len() as MoveScore;
+            let rank = square.rank().relative_to(color);
+            score += MOBILITY[piece as usize] * (moves - MOBILITY_BASE[piece as usize]);
+
+            if moves == 0 && rank >= Rank::Fifth {
+                score -= TRAPPED_PIECE[piece as usize];
+            }
+
+            // Outpost: defended by an own pawn, and no enemy pawn on the neighbouring files in front
+            let defended = !(get_pawn_attacks(square, !color) & own_pawns).is_empty();
+            let ahead = BitBoard(ranks_ahead(square, color));
+            if (Rank::Fourth..=Rank::Sixth).contains(&rank)
+                && defended
+                && (enemy_pawns & square.file().adjacent() & ahead).is_empty()
+            {
+                score += OUTPOST[piece as usize];
+            }
+
+            // A rook behind a passed pawn of either color, with nothing in between
+            if piece == Piece::Rook {
+                for pawn in attacked & passed & square.file().bitboard() {
+                    let pawn_color = if own_pawns.has(pawn) { color } else { !color };
+                    if ranks_ahead(square, pawn_color) & pawn.bitboard().0 != 0 {
+                        score += ROOK_BEHIND_PASSER;
+                    }
+                }
+            }
+        }
+    }
+
+    if b.colored_pieces(color, Piece::Bishop).len() >= 2 {
+        score += BISHOP_PAIR;
+    }
+
+    let pieces = b.colors(color) & !b.pieces(Piece::Pawn) & !b.pieces(Piece::King);
+    let hanging = pieces & attacks(b, !color) & (!attacks(b, color) | enemy_pawn_attacks);
+    score -= HANGING_PIECE * hanging.len() as MoveScore;
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn activity(fen: &str, color: Color) -> PhaseScore {
+        let b = Board::from_str(fen).unwrap();
+        let passed = passed_pawns(&b, Color::White) | passed_pawns(&b, Color::Black);
+        piece_activity(&b, color, passed)
+    }
+
+    #[test]
+    fn test_attacks() {
+        let b = Board::from_str("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1").unwrap();
+        let attacked = attacks(&b, Color::White);
+        assert!(attacked.has(Square::D3));
+        assert!(attacked.has(Square::A8));
+        assert!(attacked.has(Square::D1));
+        assert!(!attacked.has(Square::E3));
+    }
+
+    #[test]
+    fn test_mobility() {
+        let center = activity("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1", Color::White);
+        let corner = activity("4k3/8/8/8/8/8/8/N3K3 w - - 0 1", Color::White);
+        assert_eq!(center - corner, MOBILITY[Piece::Knight as usize] * 6);
+
+        // squares attacked by enemy pawns are not safe
+        let blocked = activity("4k3/8/2p1p3/8/3N4/8/8/4K3 w - - 0 1", Color::White);
+        assert_eq!(center - blocked, MOBILITY[Piece::Knight as usize] * 2);
+    }
+
+    #[test]
+    fn test_outpost() {
+        let outpost = activity("4k3/8/8/3N4/4P3/8/8/4K3 w - - 0 1", Color::White);
+        let undefended = activity("4k3/8/8/3N4/8/8/7P/4K3 w - - 0 1", Color::White);
+        assert_eq!(outpost - undefended, OUTPOST[Piece::Knight as usize]);
+
+        // c7 can drive the knight away, and takes b6 from it
+        let attackable = activity("4k3/2p5/8/3N4/4P3/8/8/4K3 w - - 0 1", Color::White);
+        assert_eq!(
+            outpost - attackable,
+            OUTPOST[Piece::Knight as usize] + MOBILITY[Piece::Knight as usize]
+        );
+    }
+
+    #[test]
+    fn test_bishop_pair() {
+        let pair = activity("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", Color::White);
+        let single = activity("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", Color::White);
+        assert_eq!(
+            pair - single,
+            BISHOP_PAIR
+                + MOBILITY[Piece::Bishop as usize] * (7 - MOBILITY_BASE[Piece::Bishop as usize])
+        );
+    }
+
+    #[test]
+    fn test_rook_behind_passer() {
+        let behind = activity("4k3/8/8/3P4/8/8/8/3RK3 w - - 0 1", Color::White);
+        let beside = activity("4k3/8/8/3P4/8/8/8/2R1K3 w - - 0 1", Color::White);
+        let mobility = MOBILITY[Piece::Rook as usize];
+        assert_eq!(behind - beside, ROOK_BEHIND_PASSER - mobility * 4);
+
+        // behind an enemy passed pawn, not in front of it
+        let behind = activity("3R4/8/8/k7/3p4/8/8/4K3 w - - 0 1", Color::White);
+        let in_front = activity("8/8/8/k7/3p4/8/8/3RK3 w - - 0 1", Color::White);
+        assert_eq!(behind - in_front, ROOK_BEHIND_PASSER + mobility * 5);
+    }
+
+    #[test]
+    fn test_trapped_piece() {
+        // the knight on a8 cannot leave
+        let trapped = activity("N3k3/p1P5/8/8/8/8/8/4K3 w - - 0 1", Color::White);
+        assert_eq!(
+            trapped,
+            PhaseScore::default()
+                - MOBILITY[Piece::Knight as usize] * MOBILITY_BASE[Piece::Knight as usize]
+                - TRAPPED_PIECE[Piece::Knight as usize]
+        );
+
+        // in the own half it is not trapped
+        let cornered = activity("4k3/8/8/8/2p5/8/2P5/N3K3 w - - 0 1", Color::White);
+        assert_eq!(cornered - trapped, TRAPPED_PIECE[Piece::Knight as usize]);
+    }
+
+    #[test]
+    fn test_hanging_piece() {
+        let defended = activity("k3r3/8/8/8/8/4N3/5K2/8 w - - 0 1", Color::White);
+        let hanging = activity("k3r3/8/8/8/8/4N3/8/7K w - - 0 1", Color::White);
+        assert_eq!(defended - hanging, HANGING_PIECE);
+
+        // attacked by a pawn, even if defended
+        let pawn = activity("k7/8/8/8/3p4/4N3/5K2/8 w - - 0 1", Color::White);
+        assert_eq!(defended - pawn, HANGING_PIECE);
+    }
+}
@@ -7,5 +7,20 @@ pub mod evaluation;
 /// A simple evaluation function
 pub mod simple;
 
+/// An evaluation function tapered between midgame and endgame
+pub mod tapered;
+
+/// King safety evaluation
+pub mod king_safety;
+
+/// Pawn structure evaluation
+pub mod pawns;
+
+/// Piece mobility and activity evaluation
+pub mod mobility;
+
+/// Cache of pawn structure evaluations
+pub mod pawn_hash;
+
 /// Collection of helpers
 pub mod helpers;
@@ -0,0 +1,128 @@
+use super::{constants::PAWN_HASH_BITS, pawns::*, tapered::PhaseScore};
+use cozy_chess::{BitBoard, Board, Color, Piece};
+use std::ops::AddAssign;
+
+/// The pawn structure of a position, depending on the pawns only.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PawnEntry {
+    white_pawns: BitBoard,
+    black_pawns: BitBoard,
+    /// Pawn structure score from White's point of view
+    pub score: PhaseScore,
+    /// Passed pawns of White and Black
+    pub passed: [BitBoard; 2],
+}
+
+impl PawnEntry {
+    /// Evaluate the pawn structure of the given board.
+    pub fn new(b: &Board) -> Self {
+        Self {
+            white_pawns: b.colored_pieces(Color::White, Piece::Pawn),
+            black_pawns: b.colored_pieces(Color::Black, Piece::Pawn),
+            score: pawn_structure(b, Color::White) - pawn_structure(b, Color::Black),
+            passed: [passed_pawns(b, Color::White), passed_pawns(b, Color::Black)],
+        }
+    }
+}
+
+/// Number of probes and hits of the pawn hash table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PawnHashStats {
+    pub probes: u64,
+    pub hits: u64,
+}
+
+impl PawnHashStats {
+    /// Give the share of probes which were hits, in percent.
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            return 0.0;
+        }
+        self.hits as f64 * 100.0 / self.probes as f64
+    }
+}
+
+impl AddAssign for PawnHashStats {
+    fn add_assign(&mut self, other: Self) {
+        self.probes += other.probes;
+        self.hits += other.hits;
+    }
+}
+
+/// A cache of pawn structure evaluations, keyed on the pawns of both colors.
+/// Every search thread has its own table, so it is used without locking.
+/// The table is allocated when it is probed first.
+#[derive(Default)]
+pub struct PawnHash {
+    entries: Vec<Option<PawnEntry>>,
+    stats: PawnHashStats,
+}
+
+impl PawnHash {
+    fn index(white_pawns: BitBoard, black_pawns: BitBoard) -> usize {
+        let key = white_pawns.0.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ black_pawns
+                .0
+                .wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                .rotate_left(32);
+        (key >> (64 - PAWN_HASH_BITS)) as usize
+    }
+
+    /// Get the pawn structure of the given board, evaluating it if not found.
+    pub fn probe(&mut self, b: &Board) -> PawnEntry {
+        if self.entries.is_empty() {
+            self.entries = vec![None; 1 << PAWN_HASH_BITS];
+        }
+        let white_pawns = b.colored_pieces(Color::White, Piece::Pawn);
+        let black_pawns = b.colored_pieces(Color::Black, Piece::Pawn);
+        let slot = &mut self.entries[Self::index(white_pawns, black_pawns)];
+        self.stats.probes += 1;
+
+        match slot {
+            Some(e) if e.white_pawns == white_pawns && e.black_pawns == black_pawns => {
+                self.stats.hits += 1;
+                *e
+            }
+            _ => *slot.insert(PawnEntry::new(b)),
+        }
+    }
+
+    /// Give the number of probes and hits so far.
+    pub fn stats(&self) -> PawnHashStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_probe() {
+        let mut hash = PawnHash::default();
+        let b = Board::from_str("4k3/p7/1p6/3P4/6p1/8/2P3P1/4K3 w - - 0 1").unwrap();
+        let entry = hash.probe(&b);
+        assert_eq!(entry, PawnEntry::new(&b));
+        assert_eq!(
+            entry.passed[Color::White as usize],
+            passed_pawns(&b, Color::White)
+        );
+
+        // the pieces and the side to move do not matter
+        let b1 = Board::from_str("4k3/p7/1p6/3P4/6p1/2N5/2P3P1/3K4 b - - 0 1").unwrap();
+        assert_eq!(hash.probe(&b1), entry);
+        let b2 = Board::from_str("4k3/p7/1p6/3P4/6p1/2P5/6P1/4K3 w - - 0 1").unwrap();
+        assert_eq!(hash.probe(&b2), PawnEntry::new(&b2));
+        assert_eq!(hash.stats(), PawnHashStats { probes: 3, hits: 1 });
+    }
+
+    #[test]
+    fn test_hit_rate() {
+        assert_eq!(PawnHashStats::default().hit_rate(), 0.0);
+        let mut stats = PawnHashStats { probes: 8, hits: 2 };
+        assert_eq!(stats.hit_rate(), 25.0);
+        stats += PawnHashStats { probes: 2, hits: 3 };
+        assert_eq!(stats.hit_rate(), 50.0);
+    }
+}
@@ -0,0 +1,197 @@
+use super::{constants::*, helpers::*, tapered::PhaseScore};
+use crate::misc::types::*;
+use cozy_chess::{get_pawn_attacks, BitBoard, Board, Color, Piece, Rank, Square};
+
+/// Give all squares on the ranks in front of the given square, as seen by the given color.
+pub fn ranks_ahead(square: Square, color: Color) -> u64 {
+    let rank = square.rank().bitboard().0;
+    match color {
+        Color::White => north_fill(rank << 8),
+        Color::Black => south_fill(rank >> 8),
+    }
+}
+
+/// Give the square in front of a pawn of the given color.
+fn stop_square(square: Square, color: Color) -> Option<Square> {
+    square.try_offset(0, if color == Color::White { 1 } else { -1 })
+}
+
+/// Give the squares attacked by the pawns of the given color.
+pub fn pawn_attacks(b: &Board, color: Color) -> BitBoard {
+    b.colored_pieces(color, Piece::Pawn)
+        .iter()
+        .fold(BitBoard::EMPTY, |acc, square| {
+            acc | get_pawn_attacks(square, color)
+        })
+}
+
+/// Give the passed pawns of the given color, which no enemy pawn can stop.
+pub fn passed_pawns(b: &Board, color: Color) -> BitBoard {
+    let enemy_pawns = b.colored_pieces(!color, Piece::Pawn).0;
+    b.colored_pieces(color, Piece::Pawn)
+        .iter()
+        .filter(|square| {
+            let files = square.file().bitboard() | square.file().adjacent();
+            enemy_pawns & files.0 & ranks_ahead(*square, color) == 0
+        })
+        .fold(BitBoard::EMPTY, |acc, square| acc | square.bitboard())
+}
+
+/// Give the pawn structure of the given color, in centipawns: passed, candidate, connected, isolated,
+/// backward and doubled pawns. Passed pawns are scored by their rank here, as the score depends on
+/// the pawns only; see `passed_pawns_kings` for the rest.
+pub fn pawn_structure(b: &Board, color: Color) -> PhaseScore {
+    let own_pawns = b.colored_pieces(color, Piece::Pawn);
+    let enemy_pawns = b.colored_pieces(!color, Piece::Pawn);
+    let enemy_attacks = pawn_attacks(b, !color);
+    let passed = passed_pawns(b, color);
+    let mut score = PhaseScore::default();
+
+    for square in own_pawns {
+        let file = square.file().bitboard();
+        let adjacent = square.file().adjacent();
+        let ahead = BitBoard(ranks_ahead(square, color));
+        let rank = square.rank().bitboard();
+
+        if passed.has(square) {
+            let rank = square.rank().relative_to(color) as usize;
+            score += PhaseScore::new(PASSED_PAWN_MG[rank], PASSED_PAWN_EG[rank]);
+        } else if (enemy_pawns & file & ahead).is_empty() {
+            // Candidate: not opposed and at least as many own pawns beside or behind
+            // as enemy pawns in front on the neighbouring files
+            let supporters = own_pawns & adjacent & !ahead;
+            let sentries = enemy_pawns & adjacent & ahead;
+            if supporters.len() >= sentries.len() {
+                score += CANDIDATE_PAWN;
+            }
+        }
+
+        let defended = !(get_pawn_attacks(square, !color) & own_pawns).is_empty();
+        let phalanx = !(own_pawns & adjacent & rank).is_empty();
+        if defended || phalanx {
+            score += CONNECTED_PAWN;
+        }
+
+        if (own_pawns & adjacent).is_empty() {
+            score -= ISOLATED_PAWN;
+        } else if (own_pawns & adjacent & !ahead).is_empty() {
+            // Backward: no pawn can defend it, and the square in front is attacked by an enemy pawn
+            if stop_square(square, color).is_some_and(|stop| enemy_attacks.has(stop)) {
+                score -= BACKWARD_PAWN;
+            }
+        }
+    }
+
+    let doubled = multiple_on_file(own_pawns.0) as MoveScore;
+    score -= DOUBLED_PAWN * doubled;
+    score
+}
+
+/// Score the given passed pawns of the given color in the endgame by the distance of the kings
+/// and the rule of the square.
+pub fn passed_pawns_kings(b: &Board, passed: BitBoard, color: Color) -> PhaseScore {
+    let pawn_endgame =
+        (b.colors(!color) & !b.pieces(Piece::Pawn) & !b.pieces(Piece::King)).is_empty();
+    let mut score = PhaseScore::default();
+
+    for square in passed {
+        let rank = square.rank().relative_to(color) as MoveScore;
+
+        // The kings matter the more the further the pawn has advanced
+        let Some(stop) = stop_square(square, color) else {
+            continue;
+        };
+        let weight = (rank - 2).max(0);
+        score.eg += weight
+            * (PASSER_ENEMY_KING_DISTANCE * square_distance(b.king(!color), stop)
+                - PASSER_OWN_KING_DISTANCE * square_distance(b.king(color), stop));
+
+        // Rule of the square: in a pawn endgame the enemy king must reach the promotion square in time
+        if pawn_endgame {
+            let promotion = Square::new(square.file(), Rank::Eighth.relative_to(color));
+            let pawn_distance = (7 - rank).min(5);
+            let king_distance = square_distance(b.king(!color), promotion)
+                - MoveScore::from(b.side_to_move() != color);
+            if king_distance > pawn_distance {
+                score.eg += UNSTOPPABLE_PASSER;
+            }
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn structure(fen: &str) -> PhaseScore {
+        let b = Board::from_str(fen).unwrap();
+        let passed = passed_pawns(&b, Color::White);
+        pawn_structure(&b, Color::White) + passed_pawns_kings(&b, passed, Color::White)
+    }
+
+    #[test]
+    fn test_passed_pawns() {
+        let b = Board::from_str("4k3/p7/1p6/3P4/6p1/8/2P3P1/4K3 w - - 0 1").unwrap();
+        assert_eq!(passed_pawns(&b, Color::White), Square::D5.bitboard());
+        assert_eq!(passed_pawns(&b, Color::Black), Square::A7.bitboard());
+    }
+
+    #[test]
+    fn test_passed_pawn_rank() {
+        let far = structure("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1");
+        let near = structure("4k3/8/3P4/8/8/8/8/4K3 w - - 0 1");
+        assert!(near.mg > far.mg);
+        assert!(near.eg > far.eg);
+    }
+
+    #[test]
+    fn test_rule_of_the_square() {
+        // the black king catches the pawn only if it is to move
+        let caught = structure("8/8/8/8/6k1/P7/8/4K3 b - - 0 1");
+        let unstoppable = structure("8/8/8/8/6k1/P7/8/4K3 w - - 0 1");
+        assert_eq!(unstoppable.eg - caught.eg, UNSTOPPABLE_PASSER);
+
+        // a knight may stop the pawn
+        let knight = structure("8/8/8/8/6k1/P7/8/4K2n w - - 0 1");
+        assert_eq!(knight, caught);
+    }
+
+    #[test]
+    fn test_king_proximity() {
+        let near = structure("8/1k6/8/8/2PK4/8/8/8 w - - 0 1");
+        let far = structure("8/1k6/8/8/2P5/8/8/6K1 w - - 0 1");
+        assert!(near.eg > far.eg);
+        assert_eq!(near.mg, far.mg);
+    }
+
+    #[test]
+    fn test_pawn_weaknesses() {
+        let isolated = structure("4k3/p1p5/8/8/8/8/P1P5/4K3 w - - 0 1");
+        assert_eq!(
+            isolated,
+            PhaseScore::default() - ISOLATED_PAWN - ISOLATED_PAWN
+        );
+
+        let doubled = structure("4k3/p7/8/8/8/P7/P7/4K3 w - - 0 1");
+        assert_eq!(
+            doubled,
+            PhaseScore::default() - ISOLATED_PAWN - ISOLATED_PAWN - DOUBLED_PAWN
+        );
+
+        // d3 cannot advance to d4, as it is attacked by c5
+        let backward = structure("4k3/8/3p4/2p5/2P5/3P4/8/4K3 w - - 0 1");
+        assert_eq!(backward, CONNECTED_PAWN - BACKWARD_PAWN);
+    }
+
+    #[test]
+    fn test_pawn_strengths() {
+        let connected = structure("4k3/p1p5/8/8/8/8/PP6/4K3 w - - 0 1");
+        assert_eq!(connected, CONNECTED_PAWN + CONNECTED_PAWN);
+
+        // c4 is supported by b2 against b6
+        let candidate = structure("4k3/8/1p6/8/2P5/8/1P6/4K3 w - - 0 1");
+        assert_eq!(candidate, CANDIDATE_PAWN);
+    }
+}
@@ -0,0 +1,186 @@
+use super::{
+    constants::*,
+    evaluation::Evaluation,
+    helpers::*,
+    king_safety::king_safety,
+    mobility::piece_activity,
+    pawn_hash::{PawnEntry, PawnHash},
+    pawns::passed_pawns_kings,
+};
+use crate::engine::constants::PAWN_VALUE;
+use crate::misc::types::*;
+use cozy_chess::{Board, Color, Piece};
+use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
+
+/// A pair of a midgame and an endgame score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhaseScore {
+    pub mg: MoveScore,
+    pub eg: MoveScore,
+}
+
+impl PhaseScore {
+    /// Constructor
+    pub const fn new(mg: MoveScore, eg: MoveScore) -> Self {
+        Self { mg, eg }
+    }
+
+    /// Interpolate between the midgame and the endgame score by the game phase.
+    pub fn taper(self, phase: MoveScore) -> MoveScore {
+        let phase = phase.min(PHASE_MAX);
+        (self.mg * phase + self.eg * (PHASE_MAX - phase)) / PHASE_MAX
+    }
+}
+
+impl AddAssign for PhaseScore {
+    fn add_assign(&mut self, other: Self) {
+        self.mg += other.mg;
+        self.eg += other.eg;
+    }
+}
+
+impl Add for PhaseScore {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl SubAssign for PhaseScore {
+    fn sub_assign(&mut self, other: Self) {
+        self.mg -= other.mg;
+        self.eg -= other.eg;
+    }
+}
+
+impl Sub for PhaseScore {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Mul<MoveScore> for PhaseScore {
+    type Output = Self;
+
+    fn mul(self, factor: MoveScore) -> Self {
+        Self::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+#[derive(Default)]
+pub struct Tapered {}
+
+impl Tapered {
+    /// Material and piece-square values of the pieces of one color, in centipawns.
+    fn material(b: &Board, color: Color) -> PhaseScore {
+        let mut score = PhaseScore::default();
+        for piece in Piece::ALL {
+            for square in b.colored_pieces(color, piece) {
+                // The tables start at a8 as seen by White
+                let index = match color {
+                    Color::White => square as usize ^ 56,
+                    Color::Black => square as usize,
+                };
+                score += PhaseScore::new(
+                    MG_VALUES[piece as usize] + MG_TABLES[piece as usize][index],
+                    EG_VALUES[piece as usize] + EG_TABLES[piece as usize][index],
+                );
+            }
+        }
+        score
+    }
+
+    /// Evaluate the given board like `evaluate`, the pawn structure taken from the given pawn hash table.
+    pub fn evaluate_with(b: &Board, pawn_hash: &mut PawnHash) -> MoveScore {
+        Self::evaluate_pawns(b, pawn_hash.probe(b))
+    }
+
+    /// Evaluate the given board with its pawn structure, from the side to move's point of view.
+    fn evaluate_pawns(b: &Board, pawns: PawnEntry) -> MoveScore {
+        let passed = pawns.passed[Color::White as usize] | pawns.passed[Color::Black as usize];
+        let score = Self::material(b, Color::White) - Self::material(b, Color::Black)
+            + king_safety(b, Color::White)
+            - king_safety(b, Color::Black)
+            + pawns.score
+            + passed_pawns_kings(b, pawns.passed[Color::White as usize], Color::White)
+            - passed_pawns_kings(b, pawns.passed[Color::Black as usize], Color::Black)
+            + piece_activity(b, Color::White, passed)
+            - piece_activity(b, Color::Black, passed);
+        let value = score.taper(game_phase(b)) * PAWN_VALUE / 100;
+        match b.side_to_move() {
+            Color::White => value,
+            Color::Black => -value,
+        }
+    }
+}
+
+impl Evaluation for Tapered {
+    /// A static evaluation function for the given board position, from the side to move's point of view.
+    /// Material, piece-square values, king safety, pawn structure and piece activity are given as midgame
+    /// and endgame scores, interpolated by the game phase, so the score changes smoothly as pieces are exchanged.
+    /// The search takes the pawn structure from a pawn hash table instead, see `evaluate_with`.
+    fn evaluate(b: &Board) -> MoveScore {
+        Self::evaluate_pawns(b, PawnEntry::new(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cozy_chess::Square;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_taper() {
+        let score = PhaseScore::new(100, 20);
+        assert_eq!(score.taper(PHASE_MAX), 100);
+        assert_eq!(score.taper(PHASE_MAX + 2), 100);
+        assert_eq!(score.taper(0), 20);
+        assert_eq!(score.taper(PHASE_MAX / 2), 60);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let board = Board::default();
+        assert_eq!(Tapered::evaluate(&board), 0);
+
+        // the score is given for the side to move
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let board = Board::from_str(fen).unwrap();
+        let score = Tapered::evaluate(&board);
+        assert!(score < 0);
+        let board = board.null_move().unwrap();
+        assert_eq!(Tapered::evaluate(&board), -score);
+
+        // a king and pawn endgame is scored by the endgame scores only, the kings are mirrored
+        let board = Board::from_str("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1").unwrap();
+        let pawn = EG_VALUES[0]
+            + EG_TABLES[0][Square::E2 as usize ^ 56]
+            + PawnEntry::new(&board).score.eg
+            + passed_pawns_kings(&board, Square::E2.bitboard(), Color::White).eg;
+        assert_eq!(Tapered::evaluate(&board), pawn * PAWN_VALUE / 100);
+
+        // the pawn hash table gives the same scores
+        let mut pawn_hash = PawnHash::default();
+        for _ in 0..2 {
+            assert_eq!(
+                Tapered::evaluate_with(&board, &mut pawn_hash),
+                Tapered::evaluate(&board)
+            );
+        }
+        assert_eq!(pawn_hash.stats().hits, 1);
+    }
+
+    #[test]
+    fn test_evaluate_smooth() {
+        // exchanging knights shifts the score gradually, without a jump at a piece count
+        let before = "r1bqkb1r/ppp2ppp/2n2n2/4p3/2B1P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 0 1";
+        let after = "r1bqkb1r/ppp2ppp/5n2/4p3/2B1P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1";
+        let before = Tapered::evaluate(&Board::from_str(before).unwrap());
+        let after = Tapered::evaluate(&Board::from_str(after).unwrap());
+        assert!((before - after).abs() < PAWN_VALUE / 2);
+    }
+}
@@ -1,9 +1,5 @@
-use crate::cmd::cli::Cli;
+use c4_e5_chess::cmd::cli::Cli;
 use log::{info, LevelFilter};
-mod cmd;
-mod engine;
-mod eval;
-mod misc;
 
 /// Main function to be used with a UCI chess gui
 fn main() {
@@ -40,7 +40,7 @@ mod tests {
             "r4rk1/pp2qpp1/2p1bn1p/8/1bP4Q/5N1P/PPB2PP1/R1BR2K1 w - - 0 1"
         );
         assert_eq!(expected_moves, vec!["c1h6".to_string()]);
-        assert_eq!(find_best_move, true);
+        assert!(find_best_move);
 
         let epd = "r4rk1/pp2qpp1/2p1bn1p/8/1bP4Q/5N1P/PPB2PP1/R1BR2K1 w - - bm Bxh6";
         let (fen, expected_moves, find_best_move) = parse_epd(epd.to_string());
@@ -49,7 +49,7 @@ mod tests {
             "r4rk1/pp2qpp1/2p1bn1p/8/1bP4Q/5N1P/PPB2PP1/R1BR2K1 w - - 0 1"
         );
         assert_eq!(expected_moves, vec!["c1h6".to_string()]);
-        assert_eq!(find_best_move, true);
+        assert!(find_best_move);
 
         let epd = "r1b2rk1/ppp3p1/4p2p/4Qpq1/3P4/2PB4/PPK2PPP/R6R b - - am Qxg2";
         let (fen, expected_moves, find_best_move) = parse_epd(epd.to_string());
@@ -58,7 +58,7 @@ mod tests {
             "r1b2rk1/ppp3p1/4p2p/4Qpq1/3P4/2PB4/PPK2PPP/R6R b - - 0 1"
         );
         assert_eq!(expected_moves, vec!["g5g2".to_string()]);
-        assert_eq!(find_best_move, false);
+        assert!(!find_best_move);
 
         let epd = "r1b2k1r/1p4pp/p4B2/2bpN3/8/q2n4/P1P2PPP/1R1QR1K1 w - - bm Bxg7+ Qh5; id \"5.IQ.1244\";";
         let (fen, expected_moves, find_best_move) = parse_epd(epd.to_string());
@@ -70,6 +70,6 @@ mod tests {
             expected_moves == vec!["f6g7".to_string(), "d1h5".to_string()]
                 || expected_moves == vec!["d1h5".to_string(), "f6g7".to_string()]
         );
-        assert_eq!(find_best_move, true);
+        assert!(find_best_move);
     }
 }